@@ -0,0 +1,96 @@
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::process::exit;
+
+use rummikub_solver::{Board, ParseError, Piece, Set, solve_board};
+
+const SOLVED: i32 = 0;
+const UNSOLVABLE: i32 = 1;
+const INVALID_INPUT: i32 = 2;
+
+const USAGE: &str = "usage: rummikub [--json] [FILE]
+
+Arranges the pieces read from FILE, or from stdin if no file is given, into valid sets.
+Pieces are either JSON, as in [{\"type\":\"Normal\",\"domination\":1,\"color\":\"Red\"}],
+or text, as in R1 B13 O7 K5 J, where K is black and J is a joker.
+Text can also be a whole board, as in [R1 R2 R3] [B5 O5 K5].
+
+  --json  print the sets as JSON
+
+exit codes: 0 solved, 1 unsolvable, 2 invalid input";
+
+fn parse(input: &str) -> Result<Vec<Piece>, String> {
+    // Only JSON has objects in it
+    if input.contains('{') {
+        serde_json::from_str(input).map_err(|e| e.to_string())
+    } else if input.matches('[').count() > 1 {
+        // Sets of a board are put back together all at once
+        let board: Board = input.parse().map_err(|e: ParseError| e.to_string())?;
+
+        Ok(board.0.into_iter().flatten().collect())
+    } else {
+        let set: Set = input.parse().map_err(|e: ParseError| e.to_string())?;
+
+        Ok(set.0)
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    exit(INVALID_INPUT)
+}
+
+fn main() {
+    let mut json = false;
+    let mut path = None;
+
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--json" => json = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                exit(SOLVED)
+            }
+            _ if path.is_none() => path = Some(arg),
+            _ => fail(USAGE)
+        }
+    }
+
+    let input = match path.as_deref() {
+        None | Some("-") => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input)
+                .unwrap_or_else(|e| fail(&format!("can't read stdin: {}", e)));
+            input
+        }
+        Some(path) => fs::read_to_string(path)
+            .unwrap_or_else(|e| fail(&format!("can't read {}: {}", path, e)))
+    };
+
+    let pieces = parse(&input).unwrap_or_else(|e| fail(&e));
+
+    // Nothing to arrange is a mistake in the input, not a solved board
+    if pieces.is_empty() {
+        fail("no pieces given");
+    }
+
+    match solve_board(pieces) {
+        Err(e) => fail(&e.to_string()),
+        Ok(None) => {
+            eprintln!("no arrangement of the pieces into valid sets");
+            exit(UNSOLVABLE)
+        }
+        Ok(Some(sets)) => {
+            if json {
+                println!("{}", serde_json::to_string(&sets).unwrap());
+            } else {
+                for set in sets {
+                    println!("{}", Set(set));
+                }
+            }
+
+            exit(SOLVED)
+        }
+    }
+}
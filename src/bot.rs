@@ -0,0 +1,301 @@
+use std::cmp::Reverse;
+
+use rand::Rng;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use crate::{Piece, RummikubError};
+use crate::game::{Action, Game, GameState, TurnView};
+use crate::ismcts::{Budget, IsmctsPlayer};
+use crate::moves::{legal_moves_with_rules, MoveOptions};
+use crate::referee::check_turn_with_rules;
+use crate::solve_turn::{solve_turn_with_rules, Turn, TurnGoal};
+
+/// Anything that can take a turn in a game, a person or a bot
+pub trait Player {
+    fn take_turn(&mut self, view: &TurnView) -> Action;
+}
+
+/// Legal moves the random player chooses from, to keep its turns quick
+const RANDOM_CHOICES: usize = 32;
+
+/// Legal moves weighed when the solver's arrangement can't be played, as listing
+/// every one of them takes too long
+const FALLBACK_CHOICES: usize = 32;
+
+/// Plays one of the first `RANDOM_CHOICES` legal moves, the ones playing the most
+/// pieces, or draws, with the same chance for each
+pub struct RandomPlayer<R: Rng> {
+    rng: R,
+}
+
+impl<R: Rng> RandomPlayer<R> {
+    pub fn new(rng: R) -> Self {
+        RandomPlayer { rng }
+    }
+}
+
+impl<R: Rng> Player for RandomPlayer<R> {
+    fn take_turn(&mut self, view: &TurnView) -> Action {
+        let options = MoveOptions { melded: view.melded, limit: Some(RANDOM_CHOICES), distinct_played: true };
+        let mut moves: Vec<Turn> = legal_moves_with_rules(view.board.to_vec(), view.rack.to_vec(), options, view.rules)
+            .map(Iterator::collect)
+            .unwrap_or_default();
+        let choice = self.rng.gen_range(0..(moves.len() + 1));
+
+        if choice == moves.len() {
+            Action::Draw
+        } else {
+            Action::Play(moves.swap_remove(choice).sets)
+        }
+    }
+}
+
+// The initial meld worth the most points made of the pieces, if it's worth enough
+fn initial_meld(view: &TurnView, pieces: &[Piece]) -> Option<Turn> {
+    let turn = solve_turn_with_rules(vec!(), pieces.to_vec(), TurnGoal::MeldPoints, view.rules).ok()??;
+    let sets = [view.board.to_vec(), turn.sets].concat();
+
+    check_turn_with_rules(view.board, &sets, view.rack, false, view.rules).ok()?;
+
+    Some(Turn { sets, played: turn.played })
+}
+
+// The legal move scoring the most under the goal, out of the first `FALLBACK_CHOICES`
+// legal moves instead of asking the solver for one
+fn best_legal_move(view: &TurnView, pieces: &[Piece], goal: TurnGoal) -> Option<Turn> {
+    let options = MoveOptions { limit: Some(FALLBACK_CHOICES), ..MoveOptions::default() };
+    let mut moves = legal_moves_with_rules(view.board.to_vec(), pieces.to_vec(), options, view.rules).ok()?;
+
+    // Moves playing more pieces come first
+    if goal == TurnGoal::MostTiles {
+        return moves.next();
+    }
+
+    // The whole board scores the same before every move, so the board after it is compared
+    let score = |turn: &Turn| -> u32 { turn.sets.iter().flat_map(|set| goal.scores(set, view.rules)).sum() };
+    moves.min_by_key(|turn| Reverse(score(turn)))
+}
+
+// The legal move scoring the most under the goal, playing only the pieces given
+fn best_move(view: &TurnView, pieces: &[Piece], goal: TurnGoal) -> Option<Turn> {
+    if !view.melded {
+        return initial_meld(view, pieces);
+    }
+
+    let turn = solve_turn_with_rules(view.board.to_vec(), pieces.to_vec(), goal, view.rules).ok()??;
+
+    if turn.played.is_empty() {
+        return None;
+    }
+
+    match check_turn_with_rules(view.board, &turn.sets, view.rack, true, view.rules) {
+        Ok(_) => Some(turn),
+        // The arrangement found moved a joker it can't, so look through the legal moves instead
+        Err(_) => best_legal_move(view, pieces, goal)
+    }
+}
+
+fn play_or_draw(turn: Option<Turn>) -> Action {
+    match turn {
+        None => Action::Draw,
+        Some(turn) => Action::Play(turn.sets)
+    }
+}
+
+/// Plays as many pieces as it can every turn
+#[derive(Debug, Default)]
+pub struct GreedyPlayer;
+
+impl Player for GreedyPlayer {
+    fn take_turn(&mut self, view: &TurnView) -> Action {
+        play_or_draw(best_move(view, view.rack, TurnGoal::MostTiles))
+    }
+}
+
+/// Holds back its jokers until they win the game, and draws instead of playing
+/// fewer than `min_played` pieces while there's anything left to draw
+#[derive(Debug)]
+pub struct ConservativePlayer {
+    pub min_played: usize,
+}
+
+impl Default for ConservativePlayer {
+    fn default() -> Self {
+        ConservativePlayer { min_played: 3 }
+    }
+}
+
+impl Player for ConservativePlayer {
+    fn take_turn(&mut self, view: &TurnView) -> Action {
+        if let Some(turn) = best_move(view, view.rack, TurnGoal::MostTiles) {
+            if turn.played.len() == view.rack.len() {
+                return Action::Play(turn.sets);
+            }
+        }
+
+        let without_jokers: Vec<Piece> = view.rack.iter().copied()
+            .filter(|piece| matches!(piece, Piece::Normal(_)))
+            .collect();
+
+        play_or_draw(best_move(view, &without_jokers, TurnGoal::MostTiles)
+            .filter(|turn| turn.played.len() >= self.min_played || view.pool_len == 0))
+    }
+}
+
+/// Plays like the conservative player, until the pool runs low or another player
+/// is close to going out. Then it gets rid of the pieces worth the most, jokers first.
+#[derive(Debug)]
+pub struct EndgamePlayer {
+    /// Pieces left in the pool, or on the rack of another player, that start the endgame
+    pub threshold: usize,
+    pub conservative: ConservativePlayer,
+}
+
+impl Default for EndgamePlayer {
+    fn default() -> Self {
+        EndgamePlayer { threshold: 4, conservative: ConservativePlayer::default() }
+    }
+}
+
+impl Player for EndgamePlayer {
+    fn take_turn(&mut self, view: &TurnView) -> Action {
+        let endgame = view.pool_len <= self.threshold || view.rack_sizes.iter().enumerate()
+            .any(|(player, size)| player != view.player && *size <= self.threshold);
+
+        if endgame {
+            play_or_draw(best_move(view, view.rack, TurnGoal::HighestValue))
+        } else {
+            self.conservative.take_turn(view)
+        }
+    }
+}
+
+/// The built-in bots
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Strength {
+    Random,
+    Greedy,
+    Conservative,
+    Endgame,
+    /// Searches with `ISMCTS_ITERATIONS` iterations every turn
+    Ismcts,
+}
+
+/// Iterations of the search bot the strength stands for
+pub const ISMCTS_ITERATIONS: usize = 64;
+
+/// A bot of the strength, the seed is for the random choices it makes
+pub fn bot(strength: Strength, seed: u64) -> Box<dyn Player> {
+    match strength {
+        Strength::Random => Box::new(RandomPlayer::new(StdRng::seed_from_u64(seed))),
+        Strength::Greedy => Box::new(GreedyPlayer),
+        Strength::Conservative => Box::new(ConservativePlayer::default()),
+        Strength::Endgame => Box::new(EndgamePlayer::default()),
+        Strength::Ismcts => Box::new(IsmctsPlayer::new(Budget::Iterations(ISMCTS_ITERATIONS), seed))
+    }
+}
+
+/// Lets the players take turns, in the order of their seats, until the game is over
+pub fn play_game(game: &mut Game, players: &mut [Box<dyn Player>]) -> Result<GameState, RummikubError> {
+    if players.len() != game.players().len() {
+        return Err(RummikubError::InvalidPlayerCount(players.len()));
+    }
+
+    while game.state() == GameState::Playing {
+        let action = players[game.current()].take_turn(&game.view());
+        game.act(action)?;
+    }
+
+    Ok(game.state())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use crate::{Color, Piece, RuleSet};
+    use crate::bot::{best_legal_move, bot, play_game, ConservativePlayer, EndgamePlayer, GreedyPlayer, Player, Strength};
+    use crate::game::{Action, Game, GameState, TurnView};
+    use crate::solve_turn::TurnGoal;
+    use crate::validate::valid_set;
+
+    fn view<'a>(rules: &'a RuleSet, board: &'a [Vec<Piece>], rack: &'a [Piece]) -> TurnView<'a> {
+        TurnView {
+            rules,
+            board,
+            rack,
+            melded: true,
+            pool_len: 30,
+            rack_sizes: vec!(rack.len(), 10),
+            players_melded: vec!(true, true),
+            player: 0,
+        }
+    }
+
+    #[test]
+    fn hold_back_jokers_until_the_end() {
+        let rules = RuleSet::default();
+        let board = vec!(vec!(Piece::normal(4, Color::Red), Piece::normal(5, Color::Red), Piece::normal(6, Color::Red)));
+        let rack = vec!(Piece::normal(7, Color::Red), Piece::Joker, Piece::normal(1, Color::Blue));
+
+        let played = |action: Action| match action {
+            Action::Draw => 0,
+            Action::Play(sets) => sets.concat().len() - 3
+        };
+
+        assert_eq!(played(GreedyPlayer.take_turn(&view(&rules, &board, &rack))), 2);
+        assert_eq!(played(ConservativePlayer { min_played: 1 }.take_turn(&view(&rules, &board, &rack))), 1);
+        assert_eq!(played(ConservativePlayer::default().take_turn(&view(&rules, &board, &rack))), 0);
+
+        // Once the other player is about to go out, the joker is better off on the board
+        let mut endgame = view(&rules, &board, &rack);
+        endgame.rack_sizes = vec!(3, 1);
+        assert_eq!(played(EndgamePlayer::default().take_turn(&endgame)), 2);
+
+        // Going out is worth playing the joker
+        let out = vec!(Piece::normal(7, Color::Red), Piece::Joker);
+        assert_eq!(played(ConservativePlayer::default().take_turn(&view(&rules, &board, &out))), 2);
+    }
+
+    #[test]
+    fn rank_legal_moves_by_the_goal() {
+        let rules = RuleSet::default();
+        let board = vec!(vec!(
+            Piece::normal(3, Color::Red),
+            Piece::normal(3, Color::Black),
+            Piece::normal(3, Color::Blue),
+            Piece::normal(3, Color::Orange),
+        ));
+
+        // Only one of the 3s can leave the group, to start a run of two pieces from the rack
+        let rack = vec!(
+            Piece::normal(1, Color::Black),
+            Piece::normal(2, Color::Black),
+            Piece::normal(4, Color::Red),
+            Piece::normal(5, Color::Red),
+        );
+
+        let most = best_legal_move(&view(&rules, &board, &rack), &rack, TurnGoal::MostTiles).unwrap();
+        assert_eq!(most.played, rack[..2].to_vec());
+
+        let highest = best_legal_move(&view(&rules, &board, &rack), &rack, TurnGoal::HighestValue).unwrap();
+        assert_eq!(highest.played, rack[2..].to_vec());
+    }
+
+    #[test]
+    fn play_whole_games() {
+        let mut game = Game::new(4, &mut StdRng::seed_from_u64(3)).unwrap();
+        let mut players = vec!(
+            bot(Strength::Random, 1),
+            bot(Strength::Greedy, 2),
+            bot(Strength::Conservative, 3),
+            bot(Strength::Endgame, 4),
+        );
+
+        assert_ne!(play_game(&mut game, &mut players), Ok(GameState::Playing));
+        assert!(game.board().iter().all(valid_set));
+        assert_eq!(game.scores().unwrap().len(), 4);
+    }
+}
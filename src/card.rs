@@ -0,0 +1,224 @@
+use serde::{Serialize, Deserialize};
+
+use crate::{NormalPiece, Piece, RuleSet, RummikubError, Wrap};
+use crate::piece::COLORS;
+use crate::tile::{meld_tiles_by_value, Melds, Tile};
+
+pub const ACE: u8 = 1;
+pub const JACK: u8 = 11;
+pub const QUEEN: u8 = 12;
+pub const KING: u8 = 13;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Ord, PartialOrd, Serialize, Deserialize)]
+pub enum Suit {
+    Clubs,
+    Diamonds,
+    Hearts,
+    Spades
+}
+
+/// A playing card, ranks go from `ACE` to `KING`. Cards of other ranks are
+/// rejected as invalid pieces.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Card {
+    Joker,
+    Normal { rank: u8, suit: Suit }
+}
+
+/// Where an ace can go in a run
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
+pub enum Ace {
+    /// A-2-3 only
+    Low,
+    /// Q-K-A only
+    High,
+    /// Both A-2-3 and Q-K-A, but not K-A-2
+    LowOrHigh,
+    /// Runs go around the corner, as in K-A-2
+    Around,
+}
+
+/// The variable parts of the rules of card games, the default is Gin Rummy
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
+pub struct CardRules {
+    pub ace: Ace,
+    /// Decks of 52 cards shuffled together
+    pub decks: u8,
+    pub jokers: u8,
+}
+
+impl Default for CardRules {
+    fn default() -> Self {
+        CardRules { ace: Ace::Low, decks: 1, jokers: 0 }
+    }
+}
+
+impl CardRules {
+    /// The rules for the pieces the cards play as, suits are colors and ranks are
+    /// dominations. When aces are only high, they come after the king as 14.
+    pub fn rules(&self) -> RuleSet {
+        let (lowest_domination, highest_domination, wrap) = match self.ace {
+            Ace::Low => (ACE, KING, Wrap::None),
+            Ace::High => (ACE + 1, KING + 1, Wrap::None),
+            Ace::LowOrHigh => (ACE, KING, Wrap::LowestAfterHighest),
+            Ace::Around => (ACE, KING, Wrap::Around)
+        };
+
+        RuleSet {
+            lowest_domination,
+            highest_domination,
+            colors: COLORS.len() as u8,
+            copies: self.decks,
+            jokers: self.jokers,
+            min_set_length: 3,
+            wrap,
+            special_jokers: false,
+        }
+    }
+}
+
+impl Card {
+    pub fn new(rank: u8, suit: Suit) -> Self {
+        Card::Normal { rank, suit }
+    }
+}
+
+impl Tile for Card {
+    fn piece(&self, rules: &RuleSet) -> Piece {
+        match self {
+            Card::Joker => Piece::Joker,
+            Card::Normal { rank, suit } => {
+                let domination = if !(ACE..=KING).contains(rank) {
+                    // No rules have a domination of 0, so the card is rejected as the piece
+                    0
+                } else if *rank == ACE && rules.lowest_domination > ACE {
+                    rules.highest_domination
+                } else {
+                    *rank
+                };
+
+                Piece::Normal(NormalPiece { domination, color: COLORS[*suit as usize] })
+            }
+        }
+    }
+
+    /// Deadwood points of the card in Gin, face cards are worth 10 and aces 1
+    fn value(&self, _rules: &RuleSet) -> u32 {
+        match self {
+            // Gin isn't played with jokers
+            Card::Joker => 0,
+            Card::Normal { rank, .. } => (*rank).min(10) as u32
+        }
+    }
+}
+
+/// A Gin hand split into melds and the deadwood left
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Deadwood {
+    pub melds: Vec<Vec<Card>>,
+    pub deadwood: Vec<Card>,
+    pub points: u32,
+}
+
+/// Melds the hand leaving the fewest deadwood points, by the Gin Rummy rules
+pub fn deadwood(hand: Vec<Card>) -> Result<Deadwood, RummikubError> {
+    let rules = CardRules::default().rules();
+    let Melds { sets, left } = meld_tiles_by_value(hand, &rules)?;
+
+    Ok(Deadwood {
+        melds: sets,
+        points: left.iter().map(|card| card.value(&rules)).sum(),
+        deadwood: left,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::RummikubError;
+    use crate::card::{Ace, ACE, Card, CardRules, deadwood, Deadwood, JACK, KING, QUEEN, Suit};
+    use crate::tile::{solve_tiles, valid_tile_set, Tile};
+
+    #[test]
+    fn place_aces_by_the_rules() {
+        let low = vec!(Card::new(ACE, Suit::Spades), Card::new(2, Suit::Spades), Card::new(3, Suit::Spades));
+        let high = vec!(Card::new(QUEEN, Suit::Hearts), Card::new(KING, Suit::Hearts), Card::new(ACE, Suit::Hearts));
+        let around = vec!(Card::new(KING, Suit::Clubs), Card::new(ACE, Suit::Clubs), Card::new(2, Suit::Clubs));
+
+        let valid = |ace, cards: &Vec<Card>| valid_tile_set(cards, &CardRules { ace, ..CardRules::default() }.rules());
+
+        assert!(valid(Ace::Low, &low));
+        assert!(!valid(Ace::Low, &high));
+        assert!(!valid(Ace::High, &low));
+        assert!(valid(Ace::High, &high));
+        assert!(valid(Ace::LowOrHigh, &low));
+        assert!(valid(Ace::LowOrHigh, &high));
+        assert!(!valid(Ace::LowOrHigh, &around));
+        assert!(valid(Ace::Around, &around));
+
+        let aces = vec!(Card::new(ACE, Suit::Clubs), Card::new(ACE, Suit::Diamonds), Card::new(ACE, Suit::Spades));
+        assert!(valid(Ace::High, &aces));
+    }
+
+    #[test]
+    fn reject_ranks_past_the_king() {
+        let past = vec!(Card::new(QUEEN, Suit::Clubs), Card::new(KING, Suit::Clubs), Card::new(14, Suit::Clubs));
+
+        for ace in [Ace::Low, Ace::High, Ace::LowOrHigh, Ace::Around] {
+            let rules = CardRules { ace, ..CardRules::default() }.rules();
+
+            assert!(!valid_tile_set(&past, &rules));
+            assert!(!valid_tile_set(&[Card::new(0, Suit::Clubs), Card::new(ACE, Suit::Clubs), Card::new(2, Suit::Clubs)], &rules));
+            assert!(matches!(solve_tiles(past.clone(), &rules), Err(RummikubError::InvalidTile(_))));
+        }
+    }
+
+    #[test]
+    fn solve_cards() {
+        let rules = CardRules { decks: 2, jokers: 2, ..CardRules::default() }.rules();
+        let cards = vec!(
+            Card::new(JACK, Suit::Diamonds),
+            Card::new(5, Suit::Hearts),
+            Card::Joker,
+            Card::new(5, Suit::Hearts),
+            Card::new(KING, Suit::Diamonds),
+            Card::new(5, Suit::Clubs),
+            Card::new(5, Suit::Spades),
+            Card::new(6, Suit::Hearts),
+            Card::new(4, Suit::Hearts),
+        );
+
+        let sets = solve_tiles(cards, &rules).unwrap().unwrap();
+        assert_eq!(sets.len(), 3);
+        assert!(sets.iter().all(|set| valid_tile_set(set, &rules)));
+
+        // A single deck has one of every card
+        let twice = vec!(Card::new(5, Suit::Hearts), Card::new(5, Suit::Hearts));
+        assert_eq!(solve_tiles(twice, &CardRules::default().rules()), Err(RummikubError::TooManyCopies {
+            piece: Card::new(5, Suit::Hearts).piece(&CardRules::default().rules()),
+            count: 2,
+            max: 1,
+        }));
+    }
+
+    #[test]
+    fn leave_the_least_deadwood() {
+        // The nines as a group leave less deadwood than the run of hearts
+        assert_eq!(deadwood(vec!(
+            Card::new(7, Suit::Hearts),
+            Card::new(8, Suit::Hearts),
+            Card::new(9, Suit::Hearts),
+            Card::new(9, Suit::Spades),
+            Card::new(9, Suit::Diamonds),
+            Card::new(KING, Suit::Clubs),
+        )), Ok(Deadwood {
+            melds: vec!(vec!(
+                Card::new(9, Suit::Diamonds),
+                Card::new(9, Suit::Hearts),
+                Card::new(9, Suit::Spades),
+            )),
+            deadwood: vec!(Card::new(7, Suit::Hearts), Card::new(8, Suit::Hearts), Card::new(KING, Suit::Clubs)),
+            points: 25,
+        }));
+    }
+}
@@ -0,0 +1,292 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+use itertools::Itertools;
+
+use crate::{NormalPiece, Piece, RuleSet};
+use crate::validate::valid_set_with_rules;
+
+/// A valid set, with the amount of every kind of piece it's made of
+pub(crate) struct CatalogueSet {
+    pub(crate) pieces: Vec<Piece>,
+    pub(crate) kinds: Vec<(usize, u8)>,
+    /// A run that could be two shorter runs of the catalogue, worth as much
+    pub(crate) split: bool,
+}
+
+/// All the valid sets that can be made with up to a given amount of jokers
+pub(crate) struct Catalogue {
+    pub(crate) rules: RuleSet,
+    pub(crate) sets: Vec<CatalogueSet>,
+    // Indexes into sets, of every set that includes the kind
+    by_kind: Vec<Vec<usize>>,
+}
+
+thread_local! {
+    // Listing the sets takes a while, and the same few catalogues are needed
+    // over and over
+    static CATALOGUES: RefCell<HashMap<(RuleSet, usize, bool), Rc<Catalogue>>> = RefCell::new(HashMap::new());
+}
+
+// Whether the run, in order, can be cut in two runs long enough, each with a
+// normal piece. Jokers keep standing for the same pieces, so the halves are
+// worth as much as the run.
+fn splits(run: &[Piece], min_set_length: usize) -> bool {
+    let normal: Vec<&NormalPiece> = run.iter()
+        .filter_map(|piece| match piece {
+            Piece::Normal(normal) => Some(normal),
+            _ => None
+        })
+        .collect();
+
+    // Groups and sets with special jokers are never split
+    if normal.is_empty() || run.iter().any(Piece::is_special_joker)
+        || normal.iter().any(|n| n.color != normal[0].color) || !normal.iter().map(|n| n.domination).all_unique() {
+        return false;
+    }
+
+    (min_set_length..(run.len() + 1).saturating_sub(min_set_length)).any(|cut| {
+        run[..cut].iter().any(|piece| matches!(piece, Piece::Normal(_)))
+            && run[cut..].iter().any(|piece| matches!(piece, Piece::Normal(_)))
+    })
+}
+
+// Every way to replace up to `jokers` pieces of a candidate set with jokers
+fn with_jokers(pieces: &[Piece], jokers: usize, candidates: &mut Vec<Vec<Piece>>) {
+    // A set needs at least one normal piece to stand for something
+    let max_jokers = jokers.min(pieces.len() - 1);
+
+    for joker_count in 0..(max_jokers + 1) {
+        for positions in (0..pieces.len()).combinations(joker_count) {
+            let mut candidate = pieces.to_vec();
+
+            for position in positions {
+                candidate[position] = Piece::Joker;
+            }
+
+            candidates.push(candidate);
+        }
+    }
+}
+
+// Sets with a special joker, made from runs and groups of normal pieces. They are
+// listed without any other jokers, as special jokers don't share a set.
+fn with_special_jokers(rules: &RuleSet, candidates: &mut Vec<Vec<Piece>>) {
+    let normal = |domination, color| Piece::Normal(NormalPiece { domination, color });
+    let mut runs: Vec<Vec<Piece>> = Vec::new();
+    let mut groups: Vec<Vec<Piece>> = Vec::new();
+
+    for color in rules.colors() {
+        for first in rules.dominations() {
+            let mut run = vec!(normal(first, *color));
+            let mut last = first;
+
+            while let Some(next) = rules.next_domination(last).filter(|_| run.len() < rules.max_run_length()) {
+                run.push(normal(next, *color));
+                last = next;
+                runs.push(run.clone());
+            }
+        }
+    }
+
+    for domination in rules.dominations() {
+        for size in 1..(rules.max_group_length() + 1) {
+            for colors in rules.colors().iter().combinations(size) {
+                groups.push(colors.iter().map(|color| normal(domination, **color)).collect());
+            }
+        }
+    }
+
+    for half in runs.iter().chain(&groups) {
+        let mut candidate = half.clone();
+        candidate.push(Piece::MirrorJoker);
+        candidate.extend(half.iter().rev());
+        candidates.push(candidate);
+    }
+
+    for group in &groups {
+        let mut candidate = group.clone();
+        candidate.push(Piece::DoubleJoker);
+        candidates.push(candidate);
+    }
+
+    for run in &runs {
+        for i in 0..(run.len() - 1) {
+            let mut candidate = run.clone();
+            candidate.splice(i..(i + 2), [Piece::DoubleJoker]);
+            candidates.push(candidate);
+        }
+
+        // The pieces after the color change joker can be of any color
+        for i in 0..run.len() {
+            for color in rules.colors() {
+                let mut candidate = run.clone();
+                candidate[i] = Piece::ColorChangeJoker;
+
+                for piece in candidate[(i + 1)..].iter_mut() {
+                    if let Piece::Normal(n) = piece {
+                        n.color = *color;
+                    }
+                }
+
+                candidates.push(candidate);
+            }
+        }
+    }
+}
+
+impl Catalogue {
+    /// Sets with special jokers are only listed when `special_jokers` is set
+    pub(crate) fn new(jokers: usize, special_jokers: bool, rules: &RuleSet) -> Self {
+        let mut candidates = Vec::new();
+        let min_set_length = rules.min_set_length as usize;
+        let max_group_length = rules.max_group_length();
+        let normal = |domination, color| Piece::Normal(NormalPiece { domination, color });
+
+        for color in rules.colors() {
+            for first in rules.dominations() {
+                let mut run = vec!(normal(first, *color));
+                let mut last = first;
+
+                while let Some(next) = rules.next_domination(last).filter(|_| run.len() < rules.max_run_length()) {
+                    run.push(normal(next, *color));
+                    last = next;
+
+                    if run.len() >= min_set_length {
+                        with_jokers(&run, jokers, &mut candidates);
+                    }
+                }
+            }
+        }
+
+        for domination in rules.dominations() {
+            for size in 1..(max_group_length + 1) {
+                for colors in rules.colors().iter().combinations(size) {
+                    let group: Vec<Piece> = colors.iter()
+                        .map(|color| normal(domination, **color))
+                        .collect();
+
+                    for extra in 0..(jokers.min(max_group_length - size) + 1) {
+                        let mut candidate = group.clone();
+                        candidate.extend((0..extra).map(|_| Piece::Joker));
+
+                        candidates.push(candidate);
+                    }
+                }
+            }
+        }
+
+        if special_jokers && rules.special_jokers {
+            with_special_jokers(rules, &mut candidates);
+        }
+
+        let mut seen: BTreeMap<Vec<usize>, usize> = BTreeMap::new();
+        let mut sets: Vec<CatalogueSet> = Vec::new();
+
+        for candidate in candidates {
+            if !valid_set_with_rules(&candidate, rules) {
+                continue;
+            }
+
+            let mut kinds: Vec<usize> = candidate.iter().filter_map(|p| rules.kind(p)).collect();
+            kinds.sort();
+
+            let splits = splits(&candidate, min_set_length);
+
+            match seen.get(&kinds) {
+                // Only when the pieces split however the jokers are placed
+                Some(i) => sets[*i].split &= splits,
+                None => {
+                    seen.insert(kinds.clone(), sets.len());
+
+                    let mut pieces = candidate;
+                    pieces.sort_by_key(|p| rules.kind(p));
+
+                    sets.push(CatalogueSet {
+                        pieces,
+                        kinds: kinds.iter().dedup_with_count().map(|(c, k)| (*k, c as u8)).collect(),
+                        split: splits,
+                    });
+                }
+            }
+        }
+
+        // Larger sets are attempted first, so long runs are not split needlessly
+        sets.sort_by_key(|set| std::cmp::Reverse(set.pieces.len()));
+
+        let mut by_kind = vec!(Vec::new(); rules.kinds());
+
+        for (i, set) in sets.iter().enumerate() {
+            for (k, _) in &set.kinds {
+                by_kind[*k].push(i);
+            }
+        }
+
+        Catalogue { rules: rules.clone(), sets, by_kind }
+    }
+
+    /// Same as `new`, but only listed once for the same rules on every thread
+    pub(crate) fn cached(jokers: usize, special_jokers: bool, rules: &RuleSet) -> Rc<Self> {
+        let key = (rules.clone(), jokers, special_jokers && rules.special_jokers);
+
+        CATALOGUES.with(|catalogues| {
+            Rc::clone(catalogues.borrow_mut().entry(key).or_insert_with(|| Rc::new(Catalogue::new(jokers, special_jokers, rules))))
+        })
+    }
+
+    pub(crate) fn containing(&self, kind: usize) -> &[usize] {
+        &self.by_kind[kind]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use crate::{Color, Piece, RuleSet, Wrap};
+    use crate::catalogue::Catalogue;
+    use crate::validate::valid_set;
+
+    #[test]
+    fn list_distinct_valid_sets() {
+        let catalogue = Catalogue::new(0, false, &RuleSet::default());
+
+        // 66 runs for each color, and 5 groups for each domination
+        assert_eq!(catalogue.sets.len(), 66 * 4 + 5 * 13);
+        assert!(catalogue.sets.iter().all(|set| valid_set(&set.pieces)));
+
+        // The same pieces never show up twice, even if jokers could be placed differently
+        let catalogue = Catalogue::new(2, false, &RuleSet::default());
+        let distinct: BTreeSet<&Vec<(usize, u8)>> = catalogue.sets.iter().map(|set| &set.kinds).collect();
+        assert_eq!(distinct.len(), catalogue.sets.len());
+    }
+
+    #[test]
+    fn list_sets_of_the_rules() {
+        let rules = RuleSet { highest_domination: 5, colors: 2, min_set_length: 2, ..RuleSet::default() };
+
+        // 10 runs for each color, and a single group for each domination
+        assert_eq!(Catalogue::new(0, false, &rules).sets.len(), 10 * 2 + 5);
+
+        // Every domination can start a run of 2 to 4 pieces, and the run of all 5 once
+        let rules = RuleSet { wrap: Wrap::Around, ..rules };
+        assert_eq!(Catalogue::new(0, false, &rules).sets.len(), (5 * 3 + 1) * 2 + 5);
+    }
+
+    #[test]
+    fn split_long_runs() {
+        let catalogue = Catalogue::new(2, false, &RuleSet::default());
+
+        assert!(catalogue.sets.iter().all(|set| set.split == (set.pieces.len() >= 6)));
+
+        // Both jokers could be at one end, and two jokers can't be a run of their own
+        let rules = RuleSet { min_set_length: 2, ..RuleSet::default() };
+        let catalogue = Catalogue::new(2, false, &rules);
+        let split = |pieces: &[Piece]| catalogue.sets.iter().find(|set| set.pieces == pieces).unwrap().split;
+
+        assert!(split(&[Piece::normal(1, Color::Red), Piece::normal(2, Color::Red), Piece::normal(3, Color::Red), Piece::normal(4, Color::Red)]));
+        assert!(split(&[Piece::normal(1, Color::Red), Piece::normal(4, Color::Red), Piece::Joker, Piece::Joker]));
+        assert!(!split(&[Piece::normal(3, Color::Red), Piece::normal(4, Color::Red), Piece::Joker, Piece::Joker]));
+    }
+}
@@ -0,0 +1,143 @@
+use crate::{Color, NormalPiece, Piece, RuleSet};
+use crate::common::{ConsecutiveSet, first_non_joker, same_color};
+use crate::sort_set::effective_dominations;
+use crate::validate::{is_group, is_run, valid_set_with_rules};
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum SetKind {
+    Run,
+    Group,
+    /// A single piece with jokers, which can be played as either
+    Both,
+}
+
+/// What a valid set is made of
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SetInfo {
+    pub kind: SetKind,
+    /// The color of a run, `None` for a group
+    pub color: Option<Color>,
+    /// The domination of the first and last piece, jokers included. A run that wraps
+    /// around ends on a lower domination than it starts.
+    pub first: u8,
+    pub last: u8,
+    /// Jokers count as the domination they stand for
+    pub points: u32,
+    /// Colors of the pieces that can still be added to the set
+    pub free_colors: Vec<Color>,
+    /// Dominations of the pieces that can still be added to the set
+    pub free_dominations: Vec<u8>,
+}
+
+pub fn classify_set(pieces: &[Piece]) -> Option<SetInfo> {
+    classify_set_with_rules(pieces, &RuleSet::default())
+}
+
+/// Classifies a valid set, or returns `None` for an invalid one. Sets with special
+/// jokers are neither runs nor groups, so they aren't classified either. The span
+/// and points of a set that can be both are those of the group, the way the set
+/// is ordered and scored elsewhere.
+pub fn classify_set_with_rules(pieces: &[Piece], rules: &RuleSet) -> Option<SetInfo> {
+    let pieces = pieces.to_vec();
+
+    if pieces.iter().any(Piece::is_special_joker) || !valid_set_with_rules(&pieces, rules) {
+        return None;
+    }
+
+    let first_piece = *first_non_joker(&pieces)?;
+    let kind = match (is_group(&first_piece, &pieces, rules), is_run(&first_piece, &pieces, rules)) {
+        (true, true) => SetKind::Both,
+        (true, false) => SetKind::Group,
+        _ => SetKind::Run
+    };
+
+    let (color, first, last) = match kind {
+        SetKind::Run => {
+            // The set is a valid run, so it has a domination for every piece
+            let run = ConsecutiveSet::new(&pieces, rules)?;
+            (Some(first_piece.color), run.actual_first(), run.last())
+        }
+        SetKind::Group => (None, first_piece.domination, first_piece.domination),
+        SetKind::Both => (Some(first_piece.color), first_piece.domination, first_piece.domination)
+    };
+
+    let fits = |piece: NormalPiece| {
+        let mut set = pieces.clone();
+        set.push(Piece::Normal(piece));
+        valid_set_with_rules(&set, rules)
+    };
+
+    Some(SetInfo {
+        kind,
+        color,
+        first,
+        last,
+        points: effective_dominations(&pieces, rules).iter().map(|d| *d as u32).sum(),
+        free_colors: rules.colors().iter()
+            .filter(|color| fits(NormalPiece { domination: first_piece.domination, color: **color }))
+            .copied()
+            .collect(),
+        free_dominations: rules.dominations()
+            .filter(|domination| same_color(&first_piece, &pieces) &&
+                fits(NormalPiece { domination: *domination, color: first_piece.color }))
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Color, Piece, RuleSet, Wrap};
+    use crate::classify::{classify_set, classify_set_with_rules, SetInfo, SetKind};
+
+    #[test]
+    fn classify_runs_and_groups() {
+        assert_eq!(classify_set(&[
+            Piece::normal(5, Color::Red),
+            Piece::normal(6, Color::Red),
+            Piece::Joker,
+        ]), Some(SetInfo {
+            kind: SetKind::Run,
+            color: Some(Color::Red),
+            first: 4,
+            last: 6,
+            points: 15,
+            free_colors: vec!(),
+            free_dominations: vec!(3, 4, 7, 8),
+        }));
+
+        assert_eq!(classify_set(&[
+            Piece::normal(9, Color::Blue),
+            Piece::Joker,
+            Piece::normal(9, Color::Black),
+        ]), Some(SetInfo {
+            kind: SetKind::Group,
+            color: None,
+            first: 9,
+            last: 9,
+            points: 27,
+            free_colors: vec!(Color::Red, Color::Orange),
+            free_dominations: vec!(),
+        }));
+
+        let both = classify_set(&[Piece::Joker, Piece::normal(12, Color::Orange), Piece::Joker]).unwrap();
+        assert_eq!(both.kind, SetKind::Both);
+        assert_eq!(both.points, 36);
+        assert_eq!(both.free_colors, vec!(Color::Black, Color::Blue, Color::Red));
+        assert_eq!(both.free_dominations, vec!(9, 10, 11, 13));
+
+        assert_eq!(classify_set(&[Piece::normal(1, Color::Red), Piece::normal(3, Color::Red)]), None);
+    }
+
+    #[test]
+    fn classify_wrapped_runs() {
+        let rules = RuleSet { wrap: Wrap::Around, ..RuleSet::default() };
+        let run = classify_set_with_rules(&[
+            Piece::normal(1, Color::Black),
+            Piece::normal(12, Color::Black),
+            Piece::normal(13, Color::Black),
+        ], &rules).unwrap();
+
+        assert_eq!((run.kind, run.first, run.last), (SetKind::Run, 12, 1));
+        assert_eq!(run.free_dominations, vec!(2, 11));
+    }
+}
@@ -1,22 +1,34 @@
 use std::collections::BTreeSet;
-use crate::{NormalPiece, Piece};
+use crate::{NormalPiece, Piece, RuleSet};
+use crate::rules::Wrap;
 
 
 pub(crate) struct ConsecutiveSet {
     holes: u8,
     jokers: u8,
     first: u8,
-    items: u8
+    items: u8,
+    lowest: u8,
+    highest: u8,
+    wrap: Wrap
 }
 
 impl ConsecutiveSet {
-    pub(crate) fn new(pieces: &Vec<Piece>) -> Option<Self> {
+    pub(crate) fn new(pieces: &Vec<Piece>, rules: &RuleSet) -> Option<Self> {
+        let items = u8::try_from(pieces.len()).ok()?;
+
+        // A longer run would have to repeat a domination, going more than once
+        // around when runs wrap
+        if pieces.len() > rules.max_run_length() {
+            return None;
+        }
+
         let mut dom:BTreeSet<u8> = BTreeSet::new();
         let mut jokers = 0;
 
         for piece in pieces {
             if let Piece::Normal(value) = piece {
-                if dom.contains(&value.domination) {
+                if dom.contains(&value.domination) || !rules.dominations().contains(&value.domination) {
                     return None;
                 }
 
@@ -29,56 +41,118 @@ impl ConsecutiveSet {
         let mut last_value_opt:Option<u8> = None;
         let mut holes = 0;
         let mut first:u8 = 0;
+        // The first and the largest hole, with the domination right after each
+        let mut first_hole: Option<(u8, u8)> = None;
+        let mut largest_hole: Option<(u8, u8)> = None;
 
-        for domination in dom {
+        for domination in &dom {
             if let Some(last_value) = last_value_opt {
-                holes += domination - last_value - 1;
+                let hole = domination - last_value - 1;
+                holes += hole;
+
+                if first_hole.is_none() {
+                    first_hole = Some((hole, *domination));
+                }
+
+                if largest_hole.is_none_or(|(largest, _)| hole > largest) {
+                    largest_hole = Some((hole, *domination));
+                }
             }
 
             if first == 0 {
-                first = domination;
+                first = *domination;
             }
 
-            last_value_opt = Some(domination);
+            last_value_opt = Some(*domination);
+        }
+
+        // When runs wrap around, the run can start after its largest hole instead,
+        // leaving that hole out. If only the lowest domination can follow the highest,
+        // that's the hole after the lowest domination. The hole between the last and
+        // the first domination is kept out when it's as large, so runs that don't need
+        // to wrap don't.
+        let wrap_after = match rules.wrap {
+            Wrap::None => None,
+            Wrap::Around => largest_hole,
+            Wrap::LowestAfterHighest => first_hole.filter(|_| first == rules.lowest_domination)
+        };
+
+        if let (Some(last), Some((hole, after))) = (last_value_opt, wrap_after) {
+            let outside = (rules.highest_domination.checked_sub(last)?)
+                .checked_add(first.checked_sub(rules.lowest_domination)?)?;
+
+            if hole > outside {
+                holes = holes - hole + outside;
+                first = after;
+            }
         }
 
         Some(ConsecutiveSet{
             first,
             holes,
             jokers,
-            items: pieces.len() as u8
+            items,
+            lowest: rules.lowest_domination,
+            highest: rules.highest_domination,
+            wrap: rules.wrap
         })
     }
 
+    /// The lowest domination of the pieces, or the one after the hole left out when the run wraps
+    pub(crate) fn start(&self) -> u8 {
+        self.first
+    }
+
     pub(crate) fn is_valid(&self) -> bool {
         self.holes <= self.jokers
     }
 
     pub(crate) fn actual_first(&self) -> u8 {
-        let free_jokers = self.jokers - self.holes;
+        let free_jokers = self.jokers.saturating_sub(self.holes);
 
-        if self.first <= free_jokers {
-            1
-        } else {
-            self.first - free_jokers
-        }
+        // Jokers that aren't filling holes go before the first piece, as long as there's room
+        self.first.saturating_sub(free_jokers).max(self.lowest)
     }
 
     fn actual_last(&self) -> u8 {
-        let first = self.actual_first();
+        // The items already include the jokers
+        self.actual_first().saturating_add(self.items - 1)
+    }
 
-        first + self.jokers + self.items - 1
+    /// The domination the run ends on, after the jokers that don't fill holes
+    pub(crate) fn last(&self) -> u8 {
+        self.wrapped(self.actual_last())
+    }
+
+    // Dominations past the highest one start over from the lowest when runs wrap
+    fn wrapped(&self, domination: u8) -> u8 {
+        match self.wrap {
+            Wrap::None => domination,
+            Wrap::Around => (domination - self.lowest) % (self.highest - self.lowest + 1) + self.lowest,
+            Wrap::LowestAfterHighest if domination > self.highest => self.lowest,
+            Wrap::LowestAfterHighest => domination
+        }
     }
 
     pub(crate) fn available_domination(&self) -> BTreeSet<u8> {
         let mut result = BTreeSet::new();
 
-        for i in self.actual_first() .. (self.actual_last() + 1) {
-            result.insert(i as u8);
+        for i in self.actual_first()..=self.actual_last() {
+            result.insert(self.wrapped(i));
         }
 
         result
     }
+
+    /// How far into the run a domination is, the first domination being 0
+    pub(crate) fn offset(&self, domination: u8) -> u8 {
+        let first = self.actual_first();
+
+        match self.wrap {
+            Wrap::Around | Wrap::LowestAfterHighest if domination < first => self.highest - first + 1 + (domination - self.lowest),
+            _ => domination.wrapping_sub(first)
+        }
+    }
 }
 
 pub(crate) fn first_non_joker(pieces: &Vec<Piece>) -> Option<&NormalPiece> {
@@ -91,24 +165,26 @@ pub(crate) fn first_non_joker(pieces: &Vec<Piece>) -> Option<&NormalPiece> {
     None
 }
 
-pub(crate) fn same_domination(first: &NormalPiece, pieces: &Vec<Piece>) -> bool {
+pub(crate) fn same_domination(first: &NormalPiece, pieces: &[Piece]) -> bool {
     pieces.iter().all(|v| match v {
-        Piece::Joker => true,
-        Piece::Normal(normal) => first.domination == normal.domination
+        Piece::Normal(normal) => first.domination == normal.domination,
+        _ => true
     })
 }
 
-pub(crate) fn same_color(first: &NormalPiece, pieces: &Vec<Piece>) -> bool {
+pub(crate) fn same_color(first: &NormalPiece, pieces: &[Piece]) -> bool {
     pieces.iter().all(|v| match v {
-        Piece::Joker => true,
-        Piece::Normal(normal) => first.color == normal.color
+        Piece::Normal(normal) => first.color == normal.color,
+        _ => true
     })
 }
 
 #[cfg(test)]
 mod tests {
     use std::collections::BTreeSet;
+    use crate::{Color, Piece, RuleSet};
     use crate::common::ConsecutiveSet;
+    use crate::rules::Wrap;
 
     #[test]
     fn generate_correct_available_domination() {
@@ -122,7 +198,63 @@ mod tests {
             holes: 1,
             jokers: 2,
             first: 2,
-            items: 2
+            items: 4,
+            lowest: 1,
+            highest: 13,
+            wrap: Wrap::None
         }.available_domination(), set);
     }
+
+    #[test]
+    fn start_wrapped_runs_after_the_largest_hole() {
+        let rules = RuleSet { wrap: Wrap::Around, ..RuleSet::default() };
+        let set = ConsecutiveSet::new(&vec!(
+            Piece::normal(1, Color::Red),
+            Piece::normal(12, Color::Red),
+            Piece::Joker,
+        ), &rules).unwrap();
+
+        assert!(set.is_valid());
+        assert_eq!(set.actual_first(), 12);
+        assert_eq!(set.offset(12), 0);
+        assert_eq!(set.offset(13), 1);
+        assert_eq!(set.offset(1), 2);
+
+        // Without wrapping, the hole from 2 to 11 is too large
+        assert!(!ConsecutiveSet::new(&vec!(
+            Piece::normal(1, Color::Red),
+            Piece::normal(12, Color::Red),
+            Piece::Joker,
+        ), &RuleSet::default()).unwrap().is_valid());
+
+        // Runs that don't need to wrap stay as they are
+        let set = ConsecutiveSet::new(&vec!(
+            Piece::normal(1, Color::Red),
+            Piece::normal(2, Color::Red),
+            Piece::Joker,
+        ), &rules).unwrap();
+        assert_eq!(set.actual_first(), 1);
+    }
+
+    #[test]
+    fn ignore_dominations_outside_the_rules() {
+        let rules = RuleSet { lowest_domination: 3, highest_domination: 9, wrap: Wrap::Around, ..RuleSet::default() };
+
+        assert!(ConsecutiveSet::new(&vec!(
+            Piece::normal(1, Color::Red),
+            Piece::normal(9, Color::Red),
+            Piece::Joker,
+        ), &rules).is_none());
+        assert!(ConsecutiveSet::new(&vec!(Piece::Joker; 300), &rules).is_none());
+    }
+
+    #[test]
+    fn reject_runs_longer_than_a_lap() {
+        let mut lap: Vec<Piece> = (1..14).map(|d| Piece::normal(d, Color::Red)).collect();
+        lap.push(Piece::Joker);
+
+        assert!(ConsecutiveSet::new(&lap[..13].to_vec(), &RuleSet { wrap: Wrap::Around, ..RuleSet::default() }).is_some());
+        assert!(ConsecutiveSet::new(&lap, &RuleSet { wrap: Wrap::Around, ..RuleSet::default() }).is_none());
+        assert!(ConsecutiveSet::new(&lap, &RuleSet::default()).is_none());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,183 @@
+use crate::{Piece, RuleSet, RummikubError};
+
+/// A piece that shows up more times than a bag has it
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Excess {
+    pub piece: Piece,
+    pub count: usize,
+    pub max: usize,
+}
+
+/// A multiset of pieces of a game, counted by kind
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TileBag {
+    rules: RuleSet,
+    counts: Vec<usize>,
+}
+
+impl TileBag {
+    pub fn empty() -> Self {
+        TileBag::empty_for(&RuleSet::default())
+    }
+
+    pub fn empty_for(rules: &RuleSet) -> Self {
+        TileBag { rules: rules.clone(), counts: vec!(0; rules.kinds()) }
+    }
+
+    /// The 106 pieces of a game, two copies of every normal piece and two jokers
+    pub fn standard() -> Self {
+        TileBag::for_rules(&RuleSet::default())
+    }
+
+    /// Every piece of a game played by the rules
+    pub fn for_rules(rules: &RuleSet) -> Self {
+        TileBag {
+            rules: rules.clone(),
+            counts: (0..rules.kinds()).map(|k| rules.copies_of(&rules.piece(k))).collect(),
+        }
+    }
+
+    pub fn from_pieces(pieces: &[Piece]) -> Result<Self, RummikubError> {
+        let mut bag = TileBag::empty();
+
+        for piece in pieces {
+            bag.insert(*piece)?;
+        }
+
+        Ok(bag)
+    }
+
+    pub fn rules(&self) -> &RuleSet {
+        &self.rules
+    }
+
+    pub fn count(&self, piece: &Piece) -> usize {
+        self.rules.kind(piece).map_or(0, |k| self.counts[k])
+    }
+
+    pub fn len(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn insert(&mut self, piece: Piece) -> Result<(), RummikubError> {
+        self.counts[self.rules.kind(&piece).ok_or(RummikubError::InvalidTile(piece))?] += 1;
+
+        Ok(())
+    }
+
+    /// Returns false if the bag had no such piece
+    pub fn remove(&mut self, piece: &Piece) -> bool {
+        match self.rules.kind(piece) {
+            Some(k) if self.counts[k] > 0 => {
+                self.counts[k] -= 1;
+                true
+            }
+            _ => false
+        }
+    }
+
+    /// Every piece in the bag, normal pieces by color and domination, then the jokers
+    pub fn pieces(&self) -> Vec<Piece> {
+        let mut pieces = Vec::new();
+
+        for (k, count) in self.counts.iter().enumerate() {
+            for _ in 0..*count {
+                pieces.push(self.rules.piece(k));
+            }
+        }
+
+        pieces
+    }
+
+    /// The pieces given more times than the bag has them, in the order they first
+    /// show up. Pieces that can't be in the bag at all have a max of 0.
+    pub fn excess(&self, pieces: &[Piece]) -> Vec<Excess> {
+        let mut result: Vec<Excess> = Vec::new();
+
+        for piece in pieces {
+            if result.iter().any(|e| e.piece == *piece) {
+                continue;
+            }
+
+            let count = pieces.iter().filter(|p| *p == piece).count();
+            let max = self.count(piece);
+
+            if count > max {
+                result.push(Excess { piece: *piece, count, max });
+            }
+        }
+
+        result
+    }
+
+    /// Checks the pieces could all have been taken out of the bag
+    pub fn check(&self, pieces: &[Piece]) -> Result<(), RummikubError> {
+        for piece in pieces {
+            self.rules.check_piece(piece)?;
+        }
+
+        match self.excess(pieces).first() {
+            None => Ok(()),
+            Some(excess) => Err(RummikubError::TooManyCopies {
+                piece: excess.piece,
+                count: excess.count,
+                max: excess.max,
+            })
+        }
+    }
+}
+
+impl Default for TileBag {
+    fn default() -> Self {
+        TileBag::standard()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Color, NormalPiece, Piece, RummikubError};
+    use crate::deck::{Excess, TileBag};
+
+    #[test]
+    fn standard_deck_has_106_pieces() {
+        let bag = TileBag::standard();
+
+        assert_eq!(bag.len(), 106);
+        assert_eq!(bag.count(&Piece::normal(7, Color::Red)), 2);
+        assert_eq!(bag.count(&Piece::Joker), 2);
+        assert_eq!(TileBag::from_pieces(&bag.pieces()), Ok(bag));
+    }
+
+    #[test]
+    fn list_over_represented_pieces() {
+        let red_7 = Piece::normal(7, Color::Red);
+        let invalid = Piece::Normal(NormalPiece { domination: 14, color: Color::Blue });
+
+        assert_eq!(TileBag::standard().excess(&[
+            red_7, Piece::Joker, red_7, red_7, invalid, red_7, red_7,
+        ]), vec!(
+            Excess { piece: red_7, count: 5, max: 2 },
+            Excess { piece: invalid, count: 1, max: 0 },
+        ));
+    }
+
+    #[test]
+    fn reject_impossible_pieces() {
+        assert_eq!(TileBag::standard().check(&[Piece::Joker, Piece::Joker]), Ok(()));
+        assert_eq!(TileBag::standard().check(&[Piece::Joker, Piece::Joker, Piece::Joker]), Err(RummikubError::TooManyCopies {
+            piece: Piece::Joker,
+            count: 3,
+            max: 2,
+        }));
+
+        let mut bag = TileBag::empty();
+        assert_eq!(bag.insert(Piece::normal(1, Color::Black)), Ok(()));
+        assert!(bag.remove(&Piece::normal(1, Color::Black)));
+        assert!(!bag.remove(&Piece::normal(1, Color::Black)));
+        assert!(bag.is_empty());
+    }
+}
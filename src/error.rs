@@ -0,0 +1,69 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+use crate::Piece;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum RummikubError {
+    /// A normal piece with a domination or a color the rules don't have
+    InvalidTile(Piece),
+    /// More copies of a piece than the game has
+    TooManyCopies {
+        piece: Piece,
+        count: usize,
+        max: usize,
+    },
+    /// A set made only of jokers, which doesn't stand for anything
+    JokerOnlySet,
+    /// A set that is neither a run nor a group
+    InvalidSet,
+    /// A rule set that no game could be played with
+    InvalidRules(&'static str),
+    /// Games are played by 2 to 4 players
+    InvalidPlayerCount(usize),
+    /// A move made after the game ended
+    GameOver,
+    /// A set of the board after a turn that is neither a run nor a group, by its index
+    InvalidBoardSet(usize),
+    /// A piece played that the player doesn't have on their rack
+    NotOnRack(Piece),
+    /// A piece that was on the board before the turn, but isn't after it
+    TakenFromBoard(Piece),
+    /// A turn that doesn't add any piece to the board
+    NothingPlayed,
+    /// Sets on the board can't be changed before the player made their initial meld
+    BoardChangedBeforeMeld,
+    /// An initial meld worth less than it has to be
+    InitialMeldTooLow {
+        points: u32,
+        required: u32,
+    },
+    /// A joker taken from its set has to be played again with two pieces from the rack
+    RetrievedJokerNotReplayed,
+}
+
+impl Display for RummikubError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RummikubError::InvalidTile(piece) => write!(f, "invalid tile: {:?}", piece),
+            RummikubError::TooManyCopies { piece, count, max } =>
+                write!(f, "got {} copies of {:?}, but there are only {}", count, piece, max),
+            RummikubError::JokerOnlySet => write!(f, "a set made only of jokers"),
+            RummikubError::InvalidSet => write!(f, "a set that is neither a run nor a group"),
+            RummikubError::InvalidRules(reason) => write!(f, "invalid rules: {}", reason),
+            RummikubError::InvalidPlayerCount(players) => write!(f, "{} players, a game needs 2 to 4", players),
+            RummikubError::GameOver => write!(f, "the game is over"),
+            RummikubError::InvalidBoardSet(set) => write!(f, "set {} of the board is neither a run nor a group", set),
+            RummikubError::NotOnRack(piece) => write!(f, "{:?} is not on the rack", piece),
+            RummikubError::TakenFromBoard(piece) => write!(f, "{:?} was taken off the board", piece),
+            RummikubError::NothingPlayed => write!(f, "no piece was played"),
+            RummikubError::BoardChangedBeforeMeld => write!(f, "the board can't be changed before the initial meld"),
+            RummikubError::InitialMeldTooLow { points, required } =>
+                write!(f, "an initial meld of {} points, but it has to be at least {}", points, required),
+            RummikubError::RetrievedJokerNotReplayed =>
+                write!(f, "a retrieved joker has to be played again with two pieces from the rack")
+        }
+    }
+}
+
+impl Error for RummikubError {}
@@ -0,0 +1,301 @@
+use std::fmt::{Display, Formatter};
+
+use crate::{NormalPiece, Piece, RuleSet};
+use crate::common::ConsecutiveSet;
+use crate::validate::valid_set_with_rules;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum SetProblemKind {
+    /// Fewer pieces than the rules ask for, a double joker counting as two
+    TooShort { min: usize },
+    /// A piece the rules don't have
+    InvalidTile,
+    /// There's no normal piece for the jokers to stand for
+    OnlyJokers,
+    /// Special jokers don't share a set, not even with a joker
+    TooManySpecialJokers,
+    /// The special joker can't stand for anything that makes the set valid
+    UnusableSpecialJoker,
+    /// Pieces of a group with another domination than the rest
+    MixedDominations,
+    /// Pieces of a group sharing a color
+    DuplicateColor,
+    /// Pieces past the most a group can have, one of each color
+    GroupTooLong { max: usize },
+    /// Pieces of a run with another color than the rest
+    MixedColors,
+    /// Pieces of a run sharing a domination
+    DuplicateDomination,
+    /// Dominations missing between two pieces of a run, when there aren't enough
+    /// jokers to fill every gap
+    Gap { missing: u8 },
+    /// Pieces past the most a run can have, one of each domination
+    RunTooLong { max: usize },
+}
+
+/// Why a set isn't valid, with the indexes of the pieces at fault
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SetProblem {
+    pub kind: SetProblemKind,
+    pub pieces: Vec<usize>,
+}
+
+impl Display for SetProblem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            SetProblemKind::TooShort { min } => write!(f, "a set needs at least {} pieces", min),
+            SetProblemKind::InvalidTile => write!(f, "not a piece of the game"),
+            SetProblemKind::OnlyJokers => write!(f, "a set made only of jokers"),
+            SetProblemKind::TooManySpecialJokers => write!(f, "a special joker can't share a set with another joker"),
+            SetProblemKind::UnusableSpecialJoker => write!(f, "the special joker doesn't fit in the set"),
+            SetProblemKind::MixedDominations => write!(f, "a group has to be of a single domination"),
+            SetProblemKind::DuplicateColor => write!(f, "a group can't have a color twice"),
+            SetProblemKind::GroupTooLong { max } => write!(f, "a group can't have more than {} pieces", max),
+            SetProblemKind::MixedColors => write!(f, "a run has to be of a single color"),
+            SetProblemKind::DuplicateDomination => write!(f, "a run can't have a domination twice"),
+            SetProblemKind::Gap { missing } => write!(f, "a gap of {} in the run, without enough jokers to fill it", missing),
+            SetProblemKind::RunTooLong { max } => write!(f, "a run can't have more than {} pieces", max)
+        }?;
+
+        write!(f, " at {:?}", self.pieces)
+    }
+}
+
+// Indexes of the pieces that share a key with another piece
+fn repeated<K: PartialEq>(normals: &[(usize, NormalPiece)], key: impl Fn(&NormalPiece) -> K) -> Vec<usize> {
+    normals.iter()
+        .filter(|(i, n)| normals.iter().any(|(j, other)| i != j && key(n) == key(other)))
+        .map(|(i, _)| *i)
+        .collect()
+}
+
+// The key shared by the most pieces, the first one seen on a tie
+fn most_common<K: PartialEq + Copy>(normals: &[(usize, NormalPiece)], key: impl Fn(&NormalPiece) -> K) -> (K, usize) {
+    let count = |k: K| normals.iter().filter(|(_, n)| key(n) == k).count();
+
+    normals.iter()
+        .map(|(_, n)| (key(n), count(key(n))))
+        .fold(None, |best: Option<(K, usize)>, (k, c)| match best {
+            Some((_, most)) if most >= c => best,
+            _ => Some((k, c))
+        })
+        .unwrap()
+}
+
+fn group_problems(set: &[Piece], normals: &[(usize, NormalPiece)], rules: &RuleSet, problems: &mut Vec<SetProblem>) {
+    let duplicates = repeated(normals, |n| n.color);
+
+    if !duplicates.is_empty() {
+        problems.push(SetProblem { kind: SetProblemKind::DuplicateColor, pieces: duplicates });
+    }
+
+    let max = rules.max_group_length();
+
+    if set.len() > max {
+        problems.push(SetProblem { kind: SetProblemKind::GroupTooLong { max }, pieces: (max..set.len()).collect() });
+    }
+}
+
+fn run_problems(set: &[Piece], normals: &[(usize, NormalPiece)], rules: &RuleSet, problems: &mut Vec<SetProblem>) {
+    let max = rules.max_run_length();
+
+    if set.len() > max {
+        problems.push(SetProblem { kind: SetProblemKind::RunTooLong { max }, pieces: (max..set.len()).collect() });
+    }
+
+    let duplicates = repeated(normals, |n| n.domination);
+
+    if !duplicates.is_empty() {
+        problems.push(SetProblem { kind: SetProblemKind::DuplicateDomination, pieces: duplicates });
+        return;
+    }
+
+    // Gaps only matter when there aren't enough jokers for all of them
+    let run = match ConsecutiveSet::new(&set.to_vec(), rules) {
+        Some(run) if !run.is_valid() => run,
+        _ => return
+    };
+
+    // How far into the run a domination is, going past the highest when the run wraps
+    let start = run.start();
+    let position = |domination: u8| if domination >= start {
+        domination - start
+    } else {
+        domination + rules.highest_domination + 1 - rules.lowest_domination - start
+    };
+
+    let mut in_order = normals.to_vec();
+    in_order.sort_by_key(|(_, n)| position(n.domination));
+
+    for pair in in_order.windows(2) {
+        let missing = position(pair[1].1.domination) - position(pair[0].1.domination) - 1;
+
+        if missing > 0 {
+            problems.push(SetProblem { kind: SetProblemKind::Gap { missing }, pieces: vec!(pair[0].0, pair[1].0) });
+        }
+    }
+}
+
+/// Every reason the set is invalid, or nothing for a valid set
+pub fn explain_set(pieces: &[Piece]) -> Vec<SetProblem> {
+    explain_set_with_rules(pieces, &RuleSet::default())
+}
+
+pub fn explain_set_with_rules(pieces: &[Piece], rules: &RuleSet) -> Vec<SetProblem> {
+    let set = pieces.to_vec();
+
+    if valid_set_with_rules(&set, rules) {
+        return vec!();
+    }
+
+    let problem = |kind, pieces: Vec<usize>| SetProblem { kind, pieces };
+    let all: Vec<usize> = (0..set.len()).collect();
+    let invalid: Vec<usize> = all.iter().copied().filter(|i| rules.check_piece(&set[*i]).is_err()).collect();
+
+    // Nothing else can be said about pieces that aren't in the game
+    if !invalid.is_empty() {
+        return vec!(problem(SetProblemKind::InvalidTile, invalid));
+    }
+
+    let mut problems = Vec::new();
+    let length = set.len() + set.iter().filter(|piece| **piece == Piece::DoubleJoker).count();
+    let min = rules.min_set_length as usize;
+
+    if length < min {
+        problems.push(problem(SetProblemKind::TooShort { min }, all.clone()));
+    }
+
+    let specials: Vec<usize> = all.iter().copied().filter(|i| set[*i].is_special_joker()).collect();
+    let jokers: Vec<usize> = all.iter().copied().filter(|i| set[*i].is_special_joker() || set[*i] == Piece::Joker).collect();
+
+    match specials.len() {
+        0 => {}
+        1 if jokers.len() > 1 => {
+            problems.push(problem(SetProblemKind::TooManySpecialJokers, jokers));
+            return problems;
+        }
+        1 if problems.is_empty() => return vec!(problem(SetProblemKind::UnusableSpecialJoker, specials)),
+        1 => return problems,
+        _ => {
+            problems.push(problem(SetProblemKind::TooManySpecialJokers, specials));
+            return problems;
+        }
+    }
+
+    let normals: Vec<(usize, NormalPiece)> = set.iter().enumerate().filter_map(|(i, piece)| match piece {
+        Piece::Normal(normal) => Some((i, *normal)),
+        _ => None
+    }).collect();
+
+    if normals.is_empty() {
+        problems.push(problem(SetProblemKind::OnlyJokers, all));
+        return problems;
+    }
+
+    let (color, same_color) = most_common(&normals, |n| n.color);
+    let (domination, same_domination) = most_common(&normals, |n| n.domination);
+
+    if same_domination == normals.len() && (same_color < normals.len() || set.len() <= rules.max_group_length()) {
+        group_problems(&set, &normals, rules, &mut problems);
+    } else if same_color == normals.len() {
+        run_problems(&set, &normals, rules, &mut problems);
+    } else if same_color >= same_domination {
+        let mixed = normals.iter().filter(|(_, n)| n.color != color).map(|(i, _)| *i).collect();
+        problems.push(problem(SetProblemKind::MixedColors, mixed));
+    } else {
+        let mixed = normals.iter().filter(|(_, n)| n.domination != domination).map(|(i, _)| *i).collect();
+        problems.push(problem(SetProblemKind::MixedDominations, mixed));
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Color, Piece, RuleSet};
+    use crate::explain::{explain_set, explain_set_with_rules, SetProblem, SetProblemKind};
+
+    fn problem(kind: SetProblemKind, pieces: Vec<usize>) -> SetProblem {
+        SetProblem { kind, pieces }
+    }
+
+    #[test]
+    fn explain_invalid_groups() {
+        assert_eq!(explain_set(&[Piece::normal(5, Color::Red), Piece::normal(5, Color::Blue)]), vec!(
+            problem(SetProblemKind::TooShort { min: 3 }, vec!(0, 1)),
+        ));
+
+        assert_eq!(explain_set(&[
+            Piece::normal(5, Color::Red),
+            Piece::normal(5, Color::Blue),
+            Piece::normal(5, Color::Red),
+            Piece::normal(5, Color::Black),
+            Piece::Joker,
+        ]), vec!(
+            problem(SetProblemKind::DuplicateColor, vec!(0, 2)),
+            problem(SetProblemKind::GroupTooLong { max: 4 }, vec!(4)),
+        ));
+
+        assert_eq!(explain_set(&[
+            Piece::normal(5, Color::Red),
+            Piece::normal(5, Color::Blue),
+            Piece::normal(6, Color::Orange),
+        ]), vec!(problem(SetProblemKind::MixedDominations, vec!(2))));
+
+        assert_eq!(explain_set(&[Piece::Joker, Piece::Joker, Piece::Joker]), vec!(
+            problem(SetProblemKind::OnlyJokers, vec!(0, 1, 2)),
+        ));
+
+        assert_eq!(explain_set(&[Piece::normal(5, Color::Red), Piece::Joker, Piece::MirrorJoker]), vec!(
+            problem(SetProblemKind::InvalidTile, vec!(2)),
+        ));
+    }
+
+    #[test]
+    fn explain_invalid_runs() {
+        assert_eq!(explain_set(&[
+            Piece::normal(2, Color::Blue),
+            Piece::normal(3, Color::Red),
+            Piece::normal(4, Color::Blue),
+        ]), vec!(problem(SetProblemKind::MixedColors, vec!(1))));
+
+        assert_eq!(explain_set(&[
+            Piece::normal(9, Color::Blue),
+            Piece::normal(1, Color::Blue),
+            Piece::Joker,
+            Piece::normal(4, Color::Blue),
+        ]), vec!(
+            problem(SetProblemKind::Gap { missing: 2 }, vec!(1, 3)),
+            problem(SetProblemKind::Gap { missing: 4 }, vec!(3, 0)),
+        ));
+
+        assert_eq!(explain_set(&[
+            Piece::normal(7, Color::Black),
+            Piece::normal(8, Color::Black),
+            Piece::normal(7, Color::Black),
+        ]), vec!(problem(SetProblemKind::DuplicateDomination, vec!(0, 2))));
+
+        let mut lap: Vec<Piece> = (1..14).map(|d| Piece::normal(d, Color::Red)).collect();
+        lap.push(Piece::Joker);
+        assert_eq!(explain_set(&lap), vec!(problem(SetProblemKind::RunTooLong { max: 13 }, vec!(13))));
+
+        let rules = RuleSet { special_jokers: true, ..RuleSet::default() };
+        assert_eq!(explain_set_with_rules(&[
+            Piece::normal(2, Color::Blue),
+            Piece::DoubleJoker,
+            Piece::normal(8, Color::Blue),
+        ], &rules), vec!(problem(SetProblemKind::UnusableSpecialJoker, vec!(1))));
+        assert_eq!(explain_set_with_rules(&[
+            Piece::normal(5, Color::Red),
+            Piece::Joker,
+            Piece::DoubleJoker,
+        ], &rules), vec!(problem(SetProblemKind::TooManySpecialJokers, vec!(1, 2))));
+    }
+
+    #[test]
+    fn explain_nothing_for_valid_sets() {
+        assert_eq!(explain_set(&[Piece::normal(12, Color::Orange), Piece::Joker, Piece::Joker]), vec!());
+        assert_eq!(problem(SetProblemKind::Gap { missing: 2 }, vec!(1, 3)).to_string(),
+            "a gap of 2 in the run, without enough jokers to fill it at [1, 3]");
+    }
+}
@@ -0,0 +1,360 @@
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use crate::{Piece, RuleSet, RummikubError};
+use crate::deck::TileBag;
+use crate::referee::check_turn_with_rules;
+
+/// Pieces every player is dealt at the start of a game
+pub const RACK_SIZE: usize = 14;
+pub const MIN_PLAYERS: usize = 2;
+pub const MAX_PLAYERS: usize = 4;
+
+/// A player's place in the game
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Seat {
+    pub rack: Vec<Piece>,
+    /// Only after the initial meld can the player change the sets on the board
+    pub melded: bool,
+}
+
+impl Seat {
+    /// What the pieces left on the rack are worth, jokers count as `JOKER_VALUE`
+    pub fn rack_value(&self) -> u32 {
+        self.rack.iter().map(|piece| piece.value() as u32).sum()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum GameState {
+    Playing,
+    /// The player emptied their rack
+    Won(usize),
+    /// The pool ran out and a whole round went by without anyone playing. The
+    /// player whose rack is worth the least wins, the first of them on a tie.
+    Blocked(usize),
+}
+
+impl GameState {
+    pub fn winner(&self) -> Option<usize> {
+        match self {
+            GameState::Playing => None,
+            GameState::Won(player) | GameState::Blocked(player) => Some(*player)
+        }
+    }
+}
+
+/// What a player does on their turn
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Action {
+    /// Draws a piece, or passes when the pool is empty
+    Draw,
+    /// Replaces the board
+    Play(Vec<Vec<Piece>>),
+}
+
+/// What the current player can see of the game
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TurnView<'a> {
+    pub rules: &'a RuleSet,
+    pub board: &'a [Vec<Piece>],
+    pub rack: &'a [Piece],
+    pub melded: bool,
+    /// Pieces left to draw
+    pub pool_len: usize,
+    /// How many pieces every player has on their rack, in turn order
+    pub rack_sizes: Vec<usize>,
+    /// Whether every player made their initial meld, in turn order
+    pub players_melded: Vec<bool>,
+    /// The index of the current player
+    pub player: usize,
+}
+
+/// A game from the deal to the end of the round. Players take turns either
+/// drawing a piece from the pool or playing pieces from their rack to the board.
+#[derive(Debug, Clone)]
+pub struct Game {
+    rules: RuleSet,
+    pool: Vec<Piece>,
+    board: Vec<Vec<Piece>>,
+    players: Vec<Seat>,
+    current: usize,
+    // Turns in a row that ended without playing or drawing anything
+    passes: usize,
+    state: GameState,
+}
+
+impl Game {
+    /// A game with the 106 standard pieces
+    pub fn new<R: Rng>(players: usize, rng: &mut R) -> Result<Self, RummikubError> {
+        Game::with_rules(players, RuleSet::default(), rng)
+    }
+
+    /// Shuffles every piece of the rules into the pool and deals the racks
+    pub fn with_rules<R: Rng>(players: usize, rules: RuleSet, rng: &mut R) -> Result<Self, RummikubError> {
+        rules.check()?;
+
+        if !(MIN_PLAYERS..=MAX_PLAYERS).contains(&players) {
+            return Err(RummikubError::InvalidPlayerCount(players));
+        }
+
+        let mut pool = TileBag::for_rules(&rules).pieces();
+
+        if pool.len() < players * RACK_SIZE {
+            return Err(RummikubError::InvalidRules("not enough pieces to deal every rack"));
+        }
+
+        pool.shuffle(rng);
+
+        let players = (0..players).map(|_| Seat {
+            rack: pool.split_off(pool.len() - RACK_SIZE),
+            melded: false,
+        }).collect();
+
+        Ok(Game { rules, pool, board: vec!(), players, current: 0, passes: 0, state: GameState::Playing })
+    }
+
+    /// A game in progress, where it's the turn of `current`
+    pub(crate) fn from_parts(rules: RuleSet, pool: Vec<Piece>, board: Vec<Vec<Piece>>, players: Vec<Seat>, current: usize) -> Self {
+        Game { rules, pool, board, players, current, passes: 0, state: GameState::Playing }
+    }
+
+    pub fn rules(&self) -> &RuleSet {
+        &self.rules
+    }
+
+    pub fn board(&self) -> &[Vec<Piece>] {
+        &self.board
+    }
+
+    pub fn players(&self) -> &[Seat] {
+        &self.players
+    }
+
+    /// The player whose turn it is
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    /// Pieces left to draw
+    pub fn pool_len(&self) -> usize {
+        self.pool.len()
+    }
+
+    pub fn state(&self) -> GameState {
+        self.state
+    }
+
+    pub fn view(&self) -> TurnView<'_> {
+        let seat = &self.players[self.current];
+
+        TurnView {
+            rules: &self.rules,
+            board: &self.board,
+            rack: &seat.rack,
+            melded: seat.melded,
+            pool_len: self.pool.len(),
+            rack_sizes: self.players.iter().map(|player| player.rack.len()).collect(),
+            players_melded: self.players.iter().map(|player| player.melded).collect(),
+            player: self.current,
+        }
+    }
+
+    /// Ends the turn of the current player with the action
+    pub fn act(&mut self, action: Action) -> Result<(), RummikubError> {
+        match action {
+            Action::Draw => self.draw().map(|_| ()),
+            Action::Play(board) => self.play(board)
+        }
+    }
+
+    fn end_turn(&mut self) {
+        self.current = (self.current + 1) % self.players.len();
+    }
+
+    /// Ends the turn of the current player by drawing a piece, or by passing when
+    /// the pool is empty. Returns the piece drawn.
+    pub fn draw(&mut self) -> Result<Option<Piece>, RummikubError> {
+        if self.state != GameState::Playing {
+            return Err(RummikubError::GameOver);
+        }
+
+        let piece = self.pool.pop();
+
+        match piece {
+            Some(piece) => {
+                self.players[self.current].rack.push(piece);
+                self.passes = 0;
+            }
+            None => self.passes += 1
+        }
+
+        if self.passes == self.players.len() {
+            let lowest = (0..self.players.len()).min_by_key(|p| self.players[*p].rack_value()).unwrap();
+            self.state = GameState::Blocked(lowest);
+        } else {
+            self.end_turn();
+        }
+
+        Ok(piece)
+    }
+
+    /// Ends the turn of the current player by replacing the board, when
+    /// `check_turn_with_rules` allows it
+    pub fn play(&mut self, board: Vec<Vec<Piece>>) -> Result<(), RummikubError> {
+        if self.state != GameState::Playing {
+            return Err(RummikubError::GameOver);
+        }
+
+        let player = &mut self.players[self.current];
+        let played = check_turn_with_rules(&self.board, &board, &player.rack, player.melded, &self.rules)?;
+
+        for piece in played {
+            let position = player.rack.iter().position(|p| *p == piece).unwrap();
+            player.rack.remove(position);
+        }
+
+        player.melded = true;
+        self.board = board;
+        self.passes = 0;
+
+        if self.players[self.current].rack.is_empty() {
+            self.state = GameState::Won(self.current);
+        } else {
+            self.end_turn();
+        }
+
+        Ok(())
+    }
+
+    /// The points every player scores for the round, once it's over. The others
+    /// lose what's left on their racks, and the winner gets how much more that is
+    /// than their own rack.
+    pub fn scores(&self) -> Option<Vec<i32>> {
+        let winner = self.state.winner()?;
+        let winner_value = self.players[winner].rack_value() as i32;
+
+        let mut scores: Vec<i32> = self.players.iter().map(|player| -(player.rack_value() as i32)).collect();
+        scores[winner] = scores.iter().enumerate()
+            .filter(|(p, _)| *p != winner)
+            .map(|(_, score)| -score - winner_value)
+            .sum();
+
+        Some(scores)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use crate::{Color, Piece, RummikubError};
+    use crate::game::{Game, GameState, RACK_SIZE};
+
+    fn game(players: usize) -> Game {
+        Game::new(players, &mut StdRng::seed_from_u64(1)).unwrap()
+    }
+
+    #[test]
+    fn deal_the_racks() {
+        let game = game(4);
+
+        assert_eq!(game.pool_len(), 106 - 4 * RACK_SIZE);
+        assert!(game.players().iter().all(|player| player.rack.len() == RACK_SIZE && !player.melded));
+        assert_eq!(game.state(), GameState::Playing);
+
+        let mut rng = StdRng::seed_from_u64(1);
+        assert_eq!(Game::new(1, &mut rng).err(), Some(RummikubError::InvalidPlayerCount(1)));
+        assert_eq!(Game::new(5, &mut rng).err(), Some(RummikubError::InvalidPlayerCount(5)));
+    }
+
+    #[test]
+    fn draw_until_the_pool_runs_out() {
+        let mut game = game(2);
+
+        let piece = game.draw().unwrap().unwrap();
+        assert_eq!(game.players()[0].rack.len(), RACK_SIZE + 1);
+        assert_eq!(game.players()[0].rack.last(), Some(&piece));
+        assert_eq!(game.current(), 1);
+
+        game.pool.clear();
+        game.players[0].rack = vec!(Piece::normal(13, Color::Red));
+        game.players[1].rack = vec!(Piece::normal(2, Color::Red), Piece::normal(3, Color::Red));
+
+        assert_eq!(game.draw(), Ok(None));
+        assert_eq!(game.state(), GameState::Playing);
+        assert_eq!(game.draw(), Ok(None));
+        assert_eq!(game.state(), GameState::Blocked(1));
+        assert_eq!(game.scores(), Some(vec!(-13, 8)));
+        assert_eq!(game.draw(), Err(RummikubError::GameOver));
+    }
+
+    #[test]
+    fn require_the_initial_meld() {
+        let mut game = game(2);
+        let run = vec!(Piece::normal(8, Color::Blue), Piece::normal(9, Color::Blue), Piece::normal(10, Color::Blue));
+        game.board = vec!(run.clone());
+        game.players[0].rack = vec!(
+            Piece::normal(1, Color::Red),
+            Piece::normal(2, Color::Red),
+            Piece::normal(3, Color::Red),
+            Piece::normal(11, Color::Blue),
+            Piece::normal(12, Color::Black),
+            Piece::normal(12, Color::Orange),
+            Piece::Joker,
+        );
+
+        let low = vec!(Piece::normal(1, Color::Red), Piece::normal(2, Color::Red), Piece::normal(3, Color::Red));
+        assert_eq!(game.play(vec!(run.clone(), low.clone())), Err(RummikubError::InitialMeldTooLow {
+            points: 6,
+            required: 30,
+        }));
+
+        let mut longer = run.clone();
+        longer.push(Piece::normal(11, Color::Blue));
+        assert_eq!(game.play(vec!(longer, low.clone())), Err(RummikubError::BoardChangedBeforeMeld));
+
+        let group = vec!(Piece::normal(12, Color::Black), Piece::Joker, Piece::normal(12, Color::Orange));
+        assert_eq!(game.play(vec!(run, low, group)), Ok(()));
+        assert!(game.players()[0].melded);
+        assert_eq!(game.players()[0].rack, vec!(Piece::normal(11, Color::Blue)));
+        assert_eq!(game.current(), 1);
+    }
+
+    #[test]
+    fn reject_pieces_that_are_not_there() {
+        let mut game = game(2);
+        let run = vec!(Piece::normal(8, Color::Blue), Piece::normal(9, Color::Blue), Piece::normal(10, Color::Blue));
+        game.board = vec!(run.clone());
+        game.players[0].melded = true;
+        game.players[0].rack = vec!(Piece::normal(11, Color::Blue), Piece::normal(5, Color::Red));
+
+        assert_eq!(game.play(vec!(run.clone())), Err(RummikubError::NothingPlayed));
+        assert_eq!(game.play(vec!()), Err(RummikubError::TakenFromBoard(Piece::normal(8, Color::Blue))));
+        assert_eq!(game.play(vec!(run.clone(), vec!(Piece::normal(5, Color::Red)))), Err(RummikubError::InvalidBoardSet(1)));
+
+        let mut longer = run.clone();
+        longer.push(Piece::normal(7, Color::Blue));
+        assert_eq!(game.play(vec!(longer)), Err(RummikubError::NotOnRack(Piece::normal(7, Color::Blue))));
+    }
+
+    #[test]
+    fn win_by_emptying_the_rack() {
+        let mut game = game(3);
+        let run = vec!(Piece::normal(8, Color::Blue), Piece::normal(9, Color::Blue), Piece::normal(10, Color::Blue));
+        game.board = vec!(run.clone());
+        game.players[0].melded = true;
+        game.players[0].rack = vec!(Piece::normal(11, Color::Blue));
+        game.players[1].rack = vec!(Piece::normal(4, Color::Red), Piece::Joker);
+        game.players[2].rack = vec!(Piece::normal(6, Color::Black));
+
+        let mut longer = run;
+        longer.push(Piece::normal(11, Color::Blue));
+
+        assert_eq!(game.play(vec!(longer)), Ok(()));
+        assert_eq!(game.state(), GameState::Won(0));
+        assert_eq!(game.scores(), Some(vec!(40, -34, -6)));
+        assert_eq!(game.play(vec!()), Err(RummikubError::GameOver));
+    }
+}
@@ -0,0 +1,270 @@
+use std::time::{Duration, Instant};
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+
+use crate::{Piece, RuleSet};
+use crate::bot::{GreedyPlayer, Player};
+use crate::deck::TileBag;
+use crate::game::{Action, Game, Seat, TurnView};
+use crate::moves::{legal_moves_with_rules, MoveOptions};
+
+/// How long the search goes on for every turn
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Budget {
+    Iterations(usize),
+    /// Always at least one iteration, however short
+    Time(Duration),
+}
+
+// A move made in the tree, told apart by the kinds of the pieces played. Drawing
+// plays nothing.
+type MoveKey = Vec<usize>;
+
+struct Node {
+    key: MoveKey,
+    // The player who made the move
+    player: usize,
+    visits: u32,
+    // Iterations where the move could have been made
+    available: u32,
+    reward: f64,
+    children: Vec<usize>,
+}
+
+/// Information set Monte Carlo tree search. Every iteration deals the pieces the
+/// player can't see to the other racks and the pool at random, and plays the game
+/// on from there. Moves are the legal moves playing the most pieces, and drawing.
+/// After leaving the tree, greedy bots play the game on for a few turns. Going out
+/// wins the iteration, and otherwise the player with the least left on their rack
+/// gets half a win.
+pub struct IsmctsPlayer {
+    pub budget: Budget,
+    /// Moves tried at every turn, on top of drawing
+    pub branching: usize,
+    /// Turns played after leaving the tree
+    pub rollout_turns: usize,
+    /// How much the search favors moves it tried less
+    pub exploration: f64,
+    rng: StdRng,
+}
+
+impl IsmctsPlayer {
+    pub fn new(budget: Budget, seed: u64) -> Self {
+        IsmctsPlayer { budget, branching: 6, rollout_turns: 12, exploration: 0.7, rng: StdRng::seed_from_u64(seed) }
+    }
+
+    // One of the games the player could be in, given what it can see
+    fn determinize(&mut self, view: &TurnView) -> Game {
+        let mut unseen = TileBag::for_rules(view.rules);
+
+        for piece in view.board.iter().flatten().chain(view.rack) {
+            unseen.remove(piece);
+        }
+
+        let mut pool = unseen.pieces();
+        pool.shuffle(&mut self.rng);
+
+        let seats = view.rack_sizes.iter().zip(&view.players_melded).enumerate().map(|(player, (size, melded))| {
+            let rack = if player == view.player {
+                view.rack.to_vec()
+            } else {
+                pool.split_off(pool.len() - (*size).min(pool.len()))
+            };
+
+            Seat { rack, melded: *melded }
+        }).collect();
+
+        Game::from_parts(view.rules.clone(), pool, view.board.to_vec(), seats, view.player)
+    }
+
+    // Wins for every player, once the game is played on from the position
+    fn rollout(&self, game: &mut Game) -> Vec<f64> {
+        let mut greedy = GreedyPlayer;
+
+        for _ in 0..self.rollout_turns {
+            if game.state().winner().is_some() {
+                break;
+            }
+
+            let action = greedy.take_turn(&game.view());
+
+            if game.act(action).is_err() {
+                // Drawing is always possible while the game is on
+                game.act(Action::Draw).unwrap();
+            }
+        }
+
+        let players = game.players();
+
+        if let Some(winner) = game.state().winner() {
+            return (0..players.len()).map(|p| if p == winner { 1.0 } else { 0.0 }).collect();
+        }
+
+        // Being ahead when the rollout stops is only half as good as going out
+        let lowest = players.iter().map(|seat| seat.rack_value()).min().unwrap();
+        players.iter().map(|seat| if seat.rack_value() == lowest { 0.5 } else { 0.0 }).collect()
+    }
+
+    fn iterate(&mut self, view: &TurnView, root_moves: &[(MoveKey, Action)], nodes: &mut Vec<Node>) {
+        let mut game = self.determinize(view);
+        let mut path = vec!(0);
+
+        while game.state().winner().is_none() {
+            let node = *path.last().unwrap();
+            let moves = if node == 0 { root_moves.to_vec() } else { moves(&game, self.branching) };
+
+            let untried: Vec<&(MoveKey, Action)> = moves.iter()
+                .filter(|(key, _)| !nodes[node].children.iter().any(|child| nodes[*child].key == *key))
+                .collect();
+
+            if let Some((key, action)) = untried.choose(&mut self.rng).copied() {
+                nodes.push(Node { key: key.clone(), player: game.current(), visits: 0, available: 1, reward: 0.0, children: vec!() });
+                let child = nodes.len() - 1;
+                nodes[node].children.push(child);
+
+                game.act(action.clone()).unwrap();
+                path.push(child);
+                break;
+            }
+
+            let available: Vec<usize> = nodes[node].children.iter().copied()
+                .filter(|child| moves.iter().any(|(key, _)| nodes[*child].key == *key))
+                .collect();
+
+            for child in &available {
+                nodes[*child].available += 1;
+            }
+
+            let ucb = |child: &usize| {
+                let n = &nodes[*child];
+                n.reward / n.visits as f64 + self.exploration * ((n.available as f64).ln() / n.visits as f64).sqrt()
+            };
+            let best = *available.iter().max_by(|a, b| ucb(a).total_cmp(&ucb(b))).unwrap();
+
+            let action = &moves.iter().find(|(key, _)| *key == nodes[best].key).unwrap().1;
+            game.act(action.clone()).unwrap();
+            path.push(best);
+        }
+
+        let rewards = self.rollout(&mut game);
+
+        for node in &path[1..] {
+            nodes[*node].visits += 1;
+            nodes[*node].reward += rewards[nodes[*node].player];
+        }
+    }
+}
+
+fn key(rules: &RuleSet, pieces: &[Piece]) -> MoveKey {
+    let mut kinds: MoveKey = pieces.iter().filter_map(|piece| rules.kind(piece)).collect();
+    kinds.sort();
+    kinds
+}
+
+// Drawing, and the legal moves of the current player playing the most pieces
+fn moves(game: &Game, branching: usize) -> Vec<(MoveKey, Action)> {
+    let view = game.view();
+    let options = MoveOptions { melded: view.melded, limit: Some(branching), distinct_played: true };
+    let legal: Vec<_> = legal_moves_with_rules(view.board.to_vec(), view.rack.to_vec(), options, view.rules)
+        .map(Iterator::collect)
+        .unwrap_or_default();
+
+    let mut moves = vec!((vec!(), Action::Draw));
+    moves.extend(legal.into_iter().map(|turn| (key(view.rules, &turn.played), Action::Play(turn.sets))));
+    moves
+}
+
+impl Player for IsmctsPlayer {
+    fn take_turn(&mut self, view: &TurnView) -> Action {
+        let root_moves = moves(&self.determinize(view), self.branching);
+
+        if root_moves.len() == 1 {
+            return Action::Draw;
+        }
+
+        let mut nodes = vec!(Node { key: vec!(), player: view.player, visits: 0, available: 0, reward: 0.0, children: vec!() });
+        let start = Instant::now();
+        let mut iterations = 0;
+
+        loop {
+            self.iterate(view, &root_moves, &mut nodes);
+            iterations += 1;
+
+            let done = match self.budget {
+                Budget::Iterations(limit) => iterations >= limit,
+                Budget::Time(limit) => start.elapsed() >= limit
+            };
+
+            if done {
+                break;
+            }
+        }
+
+        let best = nodes[0].children.iter().max_by_key(|child| nodes[**child].visits).unwrap();
+
+        root_moves.into_iter().find(|(key, _)| *key == nodes[*best].key).unwrap().1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{Color, Piece, RuleSet};
+    use crate::bot::Player;
+    use crate::game::{Action, TurnView};
+    use crate::ismcts::{Budget, IsmctsPlayer};
+
+    fn view<'a>(rules: &'a RuleSet, board: &'a [Vec<Piece>], rack: &'a [Piece]) -> TurnView<'a> {
+        TurnView {
+            rules,
+            board,
+            rack,
+            melded: true,
+            pool_len: 40,
+            rack_sizes: vec!(rack.len(), 14),
+            players_melded: vec!(true, false),
+            player: 0,
+        }
+    }
+
+    #[test]
+    fn go_out_when_it_can() {
+        let rules = RuleSet::default();
+        let board = vec!(vec!(Piece::normal(4, Color::Red), Piece::normal(5, Color::Red), Piece::normal(6, Color::Red)));
+        let rack = vec!(Piece::normal(7, Color::Red), Piece::normal(3, Color::Red));
+
+        let mut player = IsmctsPlayer::new(Budget::Iterations(20), 1);
+        let action = player.take_turn(&view(&rules, &board, &rack));
+
+        assert_eq!(action, Action::Play(vec!(vec!(
+            Piece::normal(3, Color::Red),
+            Piece::normal(4, Color::Red),
+            Piece::normal(5, Color::Red),
+            Piece::normal(6, Color::Red),
+            Piece::normal(7, Color::Red),
+        ))));
+    }
+
+    #[test]
+    fn search_the_same_way_with_the_same_seed() {
+        let rules = RuleSet::default();
+        let board = vec!(vec!(Piece::normal(9, Color::Blue), Piece::normal(9, Color::Black), Piece::normal(9, Color::Orange)));
+        let rack = vec!(
+            Piece::normal(9, Color::Red),
+            Piece::normal(1, Color::Blue),
+            Piece::normal(2, Color::Blue),
+            Piece::normal(3, Color::Blue),
+            Piece::normal(12, Color::Black),
+        );
+
+        let turn = |seed| IsmctsPlayer::new(Budget::Iterations(8), seed).take_turn(&view(&rules, &board, &rack));
+        assert_eq!(turn(5), turn(5));
+
+        // Nothing to play, so there's nothing to search
+        let mut player = IsmctsPlayer::new(Budget::Time(Duration::from_secs(60)), 1);
+        assert_eq!(player.take_turn(&view(&rules, &board, &[Piece::normal(1, Color::Red)])), Action::Draw);
+    }
+}
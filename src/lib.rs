@@ -1,8 +1,51 @@
+mod error;
+mod rules;
 mod piece;
+mod deck;
+mod catalogue;
 mod solve_tile;
+mod solve_turn;
 mod validate;
 mod common;
 mod sort_set;
+mod meld;
+mod solutions;
+mod rearrange;
+mod notation;
+mod okey;
+mod tile;
+mod card;
+mod classify;
+mod explain;
+mod game;
+mod referee;
+mod moves;
+mod bot;
+mod ismcts;
+mod simulate;
+mod odds;
 
-pub use piece::{Piece, NormalPiece, Color};
-pub use solve_tile::solve_board;
+pub use error::RummikubError;
+pub use classify::{classify_set, classify_set_with_rules, SetInfo, SetKind};
+pub use explain::{explain_set, explain_set_with_rules, SetProblem, SetProblemKind};
+pub use game::{Action, Game, GameState, Seat, TurnView, MAX_PLAYERS, MIN_PLAYERS, RACK_SIZE};
+pub use referee::{check_turn, check_turn_with_rules};
+pub use moves::{legal_moves, legal_moves_with_rules, LegalMoves, MoveOptions};
+pub use bot::{bot, play_game, ConservativePlayer, EndgamePlayer, GreedyPlayer, Player, RandomPlayer, Strength, ISMCTS_ITERATIONS};
+pub use ismcts::{Budget, IsmctsPlayer};
+pub use simulate::{simulate, simulate_with_rules, summarize, write_csv, write_json, GameStats, Summary};
+pub use card::{deadwood, Ace, Card, CardRules, Deadwood, Suit, ACE, JACK, KING, QUEEN};
+pub use deck::{Excess, TileBag};
+pub use odds::{draw_odds, draw_odds_with_rules, DrawOdds};
+pub use notation::{Board, ParseError, ParseErrorKind, Set};
+pub use okey::{Discard, Okey, OKEY_HAND_SIZE};
+pub use piece::{Piece, NormalPiece, Color, JOKER_VALUE};
+pub use rules::{RuleSet, Wrap};
+pub use solve_tile::{solve_board, solve_board_with_rules};
+pub use validate::{check_set, check_set_with_rules, valid_set, valid_set_with_rules};
+pub use tile::{meld_tiles, meld_tiles_by_value, solve_tiles, valid_tile_set, Melds, Tile};
+pub use sort_set::{resolve_jokers, resolve_jokers_with_rules, EffectiveTile};
+pub use solutions::{solutions, Solutions};
+pub use rearrange::{rearrange, Rearrangement, SetChange};
+pub use meld::{initial_meld, initial_meld_with_rules, set_points, set_points_with_rules, Meld, INITIAL_MELD_POINTS};
+pub use solve_turn::{solve_turn, solve_turn_for, solve_turn_with_rules, Turn, TurnGoal};
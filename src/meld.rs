@@ -0,0 +1,151 @@
+use crate::{Piece, RuleSet, RummikubError};
+use crate::solve_turn::{solve_turn_with_rules, TurnGoal};
+use crate::sort_set::effective_dominations;
+use crate::validate::valid_set_with_rules;
+
+/// Points the first play of a player has to be worth
+pub const INITIAL_MELD_POINTS: u32 = 30;
+
+/// An opening play made only from rack pieces
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Meld {
+    pub sets: Vec<Vec<Piece>>,
+    pub points: u32,
+}
+
+/// Points a valid set is worth, with every joker counted as the domination it stands
+/// for when the set is ordered. An invalid set is worth nothing.
+pub fn set_points(set: &[Piece]) -> u32 {
+    set_points_with_rules(set, &RuleSet::default())
+}
+
+/// Same as `set_points`, for a game played by other rules
+pub fn set_points_with_rules(set: &[Piece], rules: &RuleSet) -> u32 {
+    if !valid_set_with_rules(&set.to_vec(), rules) {
+        return 0;
+    }
+
+    effective_dominations(set, rules).iter().map(|d| *d as u32).sum()
+}
+
+/// Finds the opening meld worth the most points that can be made from the rack alone,
+/// without touching the board. Returns `None` when no opening of at least
+/// `INITIAL_MELD_POINTS` exists.
+pub fn initial_meld(rack: Vec<Piece>) -> Result<Option<Meld>, RummikubError> {
+    initial_meld_with_rules(rack, &RuleSet::default())
+}
+
+/// Same as `initial_meld`, for a game played by other rules
+pub fn initial_meld_with_rules(rack: Vec<Piece>, rules: &RuleSet) -> Result<Option<Meld>, RummikubError> {
+    let turn = match solve_turn_with_rules(vec!(), rack, TurnGoal::MeldPoints, rules)? {
+        None => return Ok(None),
+        Some(turn) => turn
+    };
+    let points = turn.sets.iter().map(|set| set_points_with_rules(set, rules)).sum();
+
+    if points < INITIAL_MELD_POINTS {
+        return Ok(None);
+    }
+
+    Ok(Some(Meld {
+        sets: turn.sets,
+        points,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Color, Piece, RuleSet, RummikubError, Wrap};
+    use crate::meld::{initial_meld, initial_meld_with_rules, Meld, set_points, set_points_with_rules};
+
+    #[test]
+    fn count_jokers_as_their_domination() {
+        assert_eq!(set_points(&[
+            Piece::normal(10, Color::Red),
+            Piece::Joker,
+            Piece::normal(10, Color::Blue),
+        ]), 30);
+
+        assert_eq!(set_points(&[
+            Piece::normal(11, Color::Red),
+            Piece::normal(12, Color::Red),
+            Piece::Joker,
+        ]), 33);
+
+        // A joker of a group only ever stands for the same domination
+        assert_eq!(set_points(&[
+            Piece::normal(12, Color::Red),
+            Piece::normal(12, Color::Blue),
+            Piece::Joker,
+        ]), 36);
+
+        // Nor can a joker between two pieces stand for anything else
+        assert_eq!(set_points(&[
+            Piece::normal(11, Color::Red),
+            Piece::Joker,
+            Piece::normal(13, Color::Red),
+        ]), 36);
+    }
+
+    #[test]
+    fn count_nothing_for_invalid_sets() {
+        assert_eq!(set_points(&[Piece::normal(12, Color::Red), Piece::normal(13, Color::Red)]), 0);
+
+        // Going around the run more than once
+        let mut lap: Vec<Piece> = (1..14).map(|d| Piece::normal(d, Color::Red)).collect();
+        lap.push(Piece::Joker);
+        assert_eq!(set_points_with_rules(&lap, &RuleSet { wrap: Wrap::Around, ..RuleSet::default() }), 0);
+    }
+
+    #[test]
+    fn find_opening_meld() {
+        assert_eq!(initial_meld(vec!(
+            Piece::normal(1, Color::Red),
+            Piece::normal(2, Color::Red),
+            Piece::normal(3, Color::Red),
+            Piece::normal(9, Color::Black),
+            Piece::normal(9, Color::Blue),
+            Piece::Joker,
+            Piece::normal(4, Color::Orange),
+        )), Ok(Some(Meld {
+            sets: vec!(
+                vec!(
+                    Piece::normal(1, Color::Red),
+                    Piece::normal(2, Color::Red),
+                    Piece::normal(3, Color::Red),
+                ),
+                vec!(
+                    Piece::normal(9, Color::Black),
+                    Piece::normal(9, Color::Blue),
+                    Piece::Joker,
+                ),
+            ),
+            points: 33,
+        })));
+    }
+
+    #[test]
+    fn reject_low_openings() {
+        assert_eq!(initial_meld(vec!(
+            Piece::normal(1, Color::Red),
+            Piece::normal(2, Color::Red),
+            Piece::normal(3, Color::Red),
+            Piece::normal(5, Color::Black),
+            Piece::normal(5, Color::Blue),
+            Piece::normal(5, Color::Orange),
+        )), Ok(None));
+    }
+
+    #[test]
+    fn meld_by_other_rules() {
+        let rack = vec!(
+            Piece::normal(10, Color::Red),
+            Piece::normal(11, Color::Red),
+            Piece::normal(12, Color::Red),
+        );
+
+        assert_eq!(initial_meld_with_rules(rack.clone(), &RuleSet { min_set_length: 4, ..RuleSet::default() }), Ok(None));
+        assert_eq!(initial_meld_with_rules(rack, &RuleSet { highest_domination: 11, ..RuleSet::default() }),
+            Err(RummikubError::InvalidTile(Piece::normal(12, Color::Red))));
+    }
+}
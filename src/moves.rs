@@ -0,0 +1,537 @@
+use std::collections::{BTreeSet, VecDeque};
+use std::rc::Rc;
+
+use itertools::Itertools;
+
+use crate::{NormalPiece, Piece, RuleSet, RummikubError};
+use crate::catalogue::Catalogue;
+use crate::deck::TileBag;
+use crate::referee::{check_turn_with_rules, joker_options};
+use crate::solutions::Solutions;
+use crate::solve_tile::{prepare, state, Search};
+use crate::solve_turn::{Turn, TurnGoal};
+use crate::sort_set::sort_sets;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct MoveOptions {
+    /// Whether the player made their initial meld, before it the board can't be changed
+    pub melded: bool,
+    /// Stop after finding this many moves
+    pub limit: Option<usize>,
+    /// A single arrangement for every distinct set of pieces played, instead of
+    /// every arrangement of them
+    pub distinct_played: bool,
+}
+
+impl Default for MoveOptions {
+    fn default() -> Self {
+        MoveOptions { melded: true, limit: None, distinct_played: true }
+    }
+}
+
+// Every way to take some of the pieces of every kind, taking the most first. The
+// ways to take as many pieces come in decreasing order, one at a time.
+struct Subsets {
+    counts: Vec<u8>,
+    size: usize,
+    next: Option<Vec<u8>>,
+}
+
+// Takes `size` pieces, as many as possible of the first kinds
+fn fill(counts: &[u8], chosen: &mut [u8], mut size: usize) {
+    for (count, chosen) in counts.iter().zip(chosen) {
+        *chosen = (*count as usize).min(size) as u8;
+        size -= *chosen as usize;
+    }
+}
+
+impl Subsets {
+    // Starting with the ways to take at most `size` pieces
+    fn new(counts: Vec<u8>, size: usize) -> Self {
+        let size = size.min(counts.iter().map(|count| *count as usize).sum());
+        let mut subsets = Subsets { counts, size, next: None };
+        subsets.next = subsets.first();
+        subsets
+    }
+
+    fn first(&self) -> Option<Vec<u8>> {
+        if self.size == 0 {
+            return None;
+        }
+
+        let mut chosen = vec!(0; self.counts.len());
+        fill(&self.counts, &mut chosen, self.size);
+        Some(chosen)
+    }
+
+    // The next way to take as many pieces: one less of the last kind that can
+    // spare it, with the pieces after it taken as early as possible
+    fn after(&self, chosen: &[u8]) -> Option<Vec<u8>> {
+        let mut taken = 0;
+        let mut room = 0;
+
+        for i in (0..chosen.len()).rev() {
+            if chosen[i] > 0 && room > taken {
+                let mut next = chosen.to_vec();
+                next[i] -= 1;
+                fill(&self.counts[(i + 1)..], &mut next[(i + 1)..], taken + 1);
+                return Some(next);
+            }
+
+            taken += chosen[i] as usize;
+            room += self.counts[i] as usize;
+        }
+
+        None
+    }
+}
+
+impl Iterator for Subsets {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Vec<u8>> {
+        let chosen = self.next.take()?;
+
+        self.next = self.after(&chosen).or_else(|| {
+            self.size -= 1;
+            self.first()
+        });
+
+        Some(chosen)
+    }
+}
+
+// What becomes of a joker of the board during the turn
+#[derive(Debug, Copy, Clone)]
+enum Fate {
+    // It still stands for the piece, so it's arranged as that piece
+    Kept(NormalPiece),
+    // It's played again, in a set with at least two pieces from the rack
+    Retrieved,
+}
+
+// Every way the jokers of the board could fare, keeping them first
+fn fates(board: &[Vec<Piece>], rules: &RuleSet) -> Vec<Vec<Fate>> {
+    let options: Vec<Vec<Fate>> = board.iter().flat_map(|set| {
+        let jokers = set.iter().filter(|piece| **piece == Piece::Joker).count();
+        let options = joker_options(set, rules);
+
+        // The jokers of an invalid set don't stand for anything
+        let options = if options.len() == jokers { options } else { vec!(vec!(); jokers) };
+
+        options.into_iter().map(|options| {
+            options.into_iter().map(Fate::Kept).chain(std::iter::once(Fate::Retrieved)).collect()
+        })
+    }).collect();
+
+    if options.is_empty() {
+        return vec!(vec!());
+    }
+
+    options.into_iter().multi_cartesian_product().collect()
+}
+
+// Turns the piece each kept joker was arranged as back into the joker, in every
+// set it could be in
+fn with_kept_jokers(sets: Vec<Vec<Piece>>, kept: &[NormalPiece]) -> Vec<Vec<Vec<Piece>>> {
+    let (first, rest) = match kept.split_first() {
+        Some(split) => split,
+        None => return vec!(sets),
+    };
+
+    sets.iter().enumerate()
+        .filter_map(|(i, set)| Some((i, set.iter().position(|piece| *piece == Piece::Normal(*first))?)))
+        .flat_map(|(i, position)| {
+            let mut sets = sets.clone();
+            sets[i][position] = Piece::Joker;
+            with_kept_jokers(sets, rest)
+        })
+        .collect()
+}
+
+// The kinds of every set, so arrangements telling apart only identical pieces
+// are only listed once
+fn normalized(sets: &[Vec<Piece>], rules: &RuleSet) -> Vec<Vec<usize>> {
+    let mut kinds: Vec<Vec<usize>> = sets.iter()
+        .map(|set| set.iter().filter_map(|piece| rules.kind(piece)).sorted().collect())
+        .collect();
+
+    kinds.sort();
+    kinds
+}
+
+// Arranges the board with the pieces played once the initial meld is made
+struct Arranger {
+    board: Vec<Vec<Piece>>,
+    rules: RuleSet,
+    catalogue: Rc<Catalogue>,
+    search: Search,
+    fates: Vec<Vec<Fate>>,
+}
+
+impl Arranger {
+    // Hands every arrangement to `found` until it returns true, and then returns true
+    fn arrange(&mut self, played: &[Piece], found: &mut dyn FnMut(Vec<Vec<Piece>>) -> bool) -> bool {
+        let mut from_rack = self.rules.kind_counts(played);
+
+        for fates in self.fates.clone() {
+            let kept: Vec<NormalPiece> = fates.iter()
+                .filter_map(|fate| match fate {
+                    Fate::Kept(piece) => Some(*piece),
+                    Fate::Retrieved => None,
+                })
+                .collect();
+
+            let mut required: Vec<Piece> = self.board.iter().flatten().copied().collect();
+            required.extend(kept.iter().map(|piece| Piece::Normal(*piece)));
+            required.extend(played);
+
+            // Kept jokers are arranged as their piece instead
+            for _ in &kept {
+                let position = required.iter().position(|piece| *piece == Piece::Joker).unwrap();
+                required.remove(position);
+            }
+
+            let mut state = state(&required, &[], &self.rules);
+
+            if self.search.best(&mut state).is_none() {
+                continue;
+            }
+
+            let retrieved = fates.len() - kept.len();
+
+            if self.host(&mut state, &mut from_rack, retrieved, 0, &mut Vec::new(), &kept, found) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    // Plays the retrieved jokers in sets with at least two pieces from the rack,
+    // before arranging the rest of the pieces
+    #[allow(clippy::too_many_arguments)]
+    fn host(
+        &mut self,
+        state: &mut Vec<u8>,
+        from_rack: &mut [u8],
+        retrieved: usize,
+        first: usize,
+        hosts: &mut Vec<Vec<Piece>>,
+        kept: &[NormalPiece],
+        found: &mut dyn FnMut(Vec<Vec<Piece>>) -> bool,
+    ) -> bool {
+        if retrieved == 0 {
+            // The sets hosting the jokers come last
+            let hosts = sort_sets(hosts.clone(), &self.rules).unwrap();
+            let search = Search::new(Rc::clone(&self.catalogue), TurnGoal::MostTiles);
+
+            for arrangement in Solutions::from_state(search, state.clone(), None) {
+                for sets in with_kept_jokers([arrangement, hosts.clone()].concat(), kept) {
+                    if found(sets) {
+                        return true;
+                    }
+                }
+            }
+
+            return false;
+        }
+
+        let joker = self.rules.joker_kind();
+        let catalogue = Rc::clone(&self.catalogue);
+
+        for set in catalogue.containing(joker).iter().copied().filter(|set| *set >= first) {
+            if !self.search.fits(state, set) {
+                continue;
+            }
+
+            let kinds = &catalogue.sets[set].kinds;
+            let jokers = kinds.iter().find(|(k, _)| *k == joker).unwrap().1 as usize;
+            let hosted = jokers.min(retrieved);
+            let rack_jokers = (jokers - hosted) as u8;
+
+            if from_rack[joker] < rack_jokers {
+                continue;
+            }
+
+            let used: Vec<(usize, u8)> = kinds.iter()
+                .map(|(k, count)| (*k, if *k == joker { rack_jokers } else { (*count).min(from_rack[*k]) }))
+                .collect();
+
+            if used.iter().map(|(_, count)| *count as usize).sum::<usize>() < 2 {
+                continue;
+            }
+
+            let taken = self.search.take(state, set);
+            let mut done = false;
+
+            if self.search.best(state).is_some() {
+                for (k, count) in &used {
+                    from_rack[*k] -= count;
+                }
+
+                hosts.push(catalogue.sets[set].pieces.clone());
+                done = self.host(state, from_rack, retrieved - hosted, set, hosts, kept, found);
+                hosts.pop();
+
+                for (k, count) in &used {
+                    from_rack[*k] += count;
+                }
+            }
+
+            self.search.put_back(state, set, &taken);
+
+            if done {
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+/// The legal moves of a position, found as they are asked for
+pub struct LegalMoves {
+    board: Vec<Vec<Piece>>,
+    rack: Vec<Piece>,
+    options: MoveOptions,
+    // The kinds of the rack that could be played, and how many of each to play next
+    useful: Vec<usize>,
+    candidates: Subsets,
+    arranger: Arranger,
+    pending: VecDeque<Turn>,
+    found: usize,
+}
+
+impl LegalMoves {
+    // Lists the moves playing the chosen pieces of the useful kinds
+    fn play(&mut self, chosen: &[u8]) {
+        let LegalMoves { board, rack, options, useful, arranger, pending, found, .. } = self;
+        let rules = arranger.rules.clone();
+        let mut kinds_left = vec!(0; rules.kinds());
+
+        for (k, count) in useful.iter().zip(chosen) {
+            kinds_left[*k] = *count;
+        }
+
+        // The pieces played, in the order they are on the rack
+        let played = rules.take_kinds(rack, &mut kinds_left);
+
+        let mut listed = BTreeSet::new();
+        let mut found = |sets: Vec<Vec<Piece>>| {
+            if !listed.insert(normalized(&sets, &rules)) {
+                return false;
+            }
+
+            if check_turn_with_rules(board, &sets, rack, options.melded, &rules).is_err() {
+                return false;
+            }
+
+            pending.push_back(Turn { sets, played: played.clone() });
+            options.distinct_played || options.limit.is_some_and(|limit| *found + pending.len() >= limit)
+        };
+
+        if options.melded {
+            arranger.arrange(&played, &mut found);
+            return;
+        }
+
+        // Before the initial meld, the pieces played make sets of their own
+        let mut required = state(&played, &[], &rules);
+
+        if arranger.search.best(&mut required).is_none() {
+            return;
+        }
+
+        let search = Search::new(Rc::clone(&arranger.catalogue), TurnGoal::MostTiles);
+
+        for arrangement in Solutions::from_state(search, required, None) {
+            if found([board.clone(), arrangement].concat()) {
+                break;
+            }
+        }
+    }
+}
+
+impl Iterator for LegalMoves {
+    type Item = Turn;
+
+    fn next(&mut self) -> Option<Turn> {
+        loop {
+            if self.options.limit.is_some_and(|limit| self.found >= limit) {
+                return None;
+            }
+
+            if let Some(turn) = self.pending.pop_front() {
+                self.found += 1;
+                return Some(turn);
+            }
+
+            let chosen = self.candidates.next()?;
+            self.play(&chosen);
+        }
+    }
+}
+
+pub fn legal_moves(board: Vec<Vec<Piece>>, rack: Vec<Piece>, options: MoveOptions) -> Result<LegalMoves, RummikubError> {
+    legal_moves_with_rules(board, rack, options, &RuleSet::default())
+}
+
+/// Every legal turn that plays pieces from the rack, as `check_turn_with_rules`
+/// allows it, with an arrangement of the board for each. Turns playing more
+/// pieces come first, and each one is only looked for once the ones before it
+/// were taken.
+pub fn legal_moves_with_rules(
+    board: Vec<Vec<Piece>>,
+    rack: Vec<Piece>,
+    options: MoveOptions,
+    rules: &RuleSet,
+) -> Result<LegalMoves, RummikubError> {
+    rules.check()?;
+
+    let board_pieces: Vec<Piece> = board.concat();
+    let mut all = board_pieces.clone();
+    all.extend(&rack);
+    TileBag::for_rules(rules).check(&all)?;
+
+    let (full, catalogue) = prepare(&board_pieces, &rack, rules);
+    let mut search = Search::new(Rc::clone(&catalogue), TurnGoal::MostTiles);
+    let kinds = rules.kinds();
+
+    // Pieces of the rack that aren't in any set that could be made are never played
+    let useful: Vec<usize> = (0..kinds)
+        .filter(|k| full[kinds + k] > 0 && catalogue.containing(*k).iter().any(|set| search.fits(&full, *set)))
+        .collect();
+
+    // No turn plays more pieces than the most that can be placed
+    let mut most_state = if options.melded { full.clone() } else { state(&[], &rack, rules) };
+    let most = search.best(&mut most_state).unwrap_or(0) as usize;
+
+    let candidates = Subsets::new(useful.iter().map(|k| full[kinds + k]).collect(), most);
+    let arranger = Arranger {
+        board: board.clone(),
+        rules: rules.clone(),
+        catalogue,
+        search,
+        fates: if options.melded { fates(&board, rules) } else { vec!() },
+    };
+
+    Ok(LegalMoves {
+        board,
+        rack,
+        options,
+        useful,
+        candidates,
+        arranger,
+        pending: VecDeque::new(),
+        found: 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Color, Piece};
+    use crate::moves::{legal_moves, MoveOptions, Subsets};
+    use crate::referee::check_turn;
+
+    #[test]
+    fn list_every_set_of_pieces_played() {
+        let board = vec!(vec!(Piece::normal(4, Color::Red), Piece::normal(5, Color::Red), Piece::normal(6, Color::Red)));
+        let rack = vec!(Piece::normal(3, Color::Red), Piece::normal(7, Color::Red), Piece::normal(11, Color::Blue));
+
+        let moves = legal_moves(board.clone(), rack.clone(), MoveOptions::default()).unwrap().collect::<Vec<_>>();
+        let played: Vec<Vec<Piece>> = moves.iter().map(|turn| turn.played.clone()).collect();
+
+        assert_eq!(played, vec!(
+            vec!(Piece::normal(3, Color::Red), Piece::normal(7, Color::Red)),
+            vec!(Piece::normal(3, Color::Red)),
+            vec!(Piece::normal(7, Color::Red)),
+        ));
+        assert!(moves.iter().all(|turn| check_turn(&board, &turn.sets, &rack, true).is_ok()));
+
+        let capped = MoveOptions { limit: Some(2), ..MoveOptions::default() };
+        assert_eq!(legal_moves(board.clone(), rack.clone(), capped).unwrap().count(), 2);
+
+        // Moves are only looked for as they are asked for
+        let mut lazy = legal_moves(board, rack, MoveOptions::default()).unwrap();
+        assert_eq!(lazy.next(), Some(moves[0].clone()));
+        assert_eq!(lazy.count(), 2);
+    }
+
+    #[test]
+    fn list_every_arrangement_when_asked() {
+        let rack: Vec<Piece> = [Color::Black, Color::Blue, Color::Red].iter()
+            .flat_map(|color| (1..4).map(|d| Piece::normal(d, *color)))
+            .collect();
+
+        // All nine pieces make either three runs or three groups
+        let all = MoveOptions { distinct_played: false, ..MoveOptions::default() };
+        let moves = legal_moves(vec!(), rack.clone(), all).unwrap().collect::<Vec<_>>();
+        assert_eq!(moves.iter().filter(|turn| turn.played.len() == 9).count(), 2);
+
+        let distinct = legal_moves(vec!(), rack, MoveOptions::default()).unwrap().collect::<Vec<_>>();
+        assert_eq!(distinct.iter().filter(|turn| turn.played.len() == 9).count(), 1);
+    }
+
+    #[test]
+    fn keep_to_the_initial_meld() {
+        let board = vec!(vec!(Piece::normal(4, Color::Red), Piece::normal(5, Color::Red), Piece::normal(6, Color::Red)));
+        let rack = vec!(
+            Piece::normal(7, Color::Red),
+            Piece::normal(10, Color::Black),
+            Piece::normal(10, Color::Blue),
+            Piece::normal(10, Color::Orange),
+        );
+
+        let moves = legal_moves(board.clone(), rack.clone(), MoveOptions { melded: false, ..MoveOptions::default() }).unwrap().collect::<Vec<_>>();
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].played, rack[1..].to_vec());
+        assert_eq!(moves[0].sets[0], board[0]);
+    }
+
+    #[test]
+    fn take_the_most_pieces_first() {
+        let subsets: Vec<Vec<u8>> = Subsets::new(vec!(2, 1), 3).collect();
+
+        assert_eq!(subsets, vec!(vec!(2, 1), vec!(2, 0), vec!(1, 1), vec!(1, 0), vec!(0, 1)));
+        assert_eq!(Subsets::new(vec!(2, 1), 1).count(), 2);
+    }
+
+    #[test]
+    fn play_a_retrieved_joker_again() {
+        let board = vec!(vec!(Piece::normal(5, Color::Red), Piece::Joker, Piece::normal(7, Color::Red)));
+        let rack = vec!(Piece::normal(6, Color::Red), Piece::normal(9, Color::Blue), Piece::normal(9, Color::Orange));
+
+        let moves = legal_moves(board.clone(), rack.clone(), MoveOptions::default()).unwrap().collect::<Vec<_>>();
+
+        // Playing the red 6 alone would leave the joker without a set to be played in
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].played, rack);
+        assert!(moves[0].sets.iter().any(|set| set.contains(&Piece::Joker) && set.contains(&Piece::normal(9, Color::Blue))));
+        assert!(moves.iter().all(|turn| check_turn(&board, &turn.sets, &rack, true).is_ok()));
+
+        let extended = legal_moves(board.clone(), vec!(Piece::normal(8, Color::Red)), MoveOptions::default()).unwrap().collect::<Vec<_>>();
+        assert_eq!(extended.len(), 1);
+        assert!(extended[0].sets[0].contains(&Piece::Joker));
+    }
+
+    #[test]
+    fn host_a_retrieved_joker_with_every_piece() {
+        let board = vec!(vec!(Piece::normal(9, Color::Blue), Piece::Joker, Piece::normal(11, Color::Blue)));
+        let rack = vec!(Piece::normal(10, Color::Blue), Piece::normal(12, Color::Blue));
+
+        let all = vec!(vec!(
+            Piece::normal(9, Color::Blue),
+            Piece::normal(10, Color::Blue),
+            Piece::normal(11, Color::Blue),
+            Piece::normal(12, Color::Blue),
+            Piece::Joker,
+        ));
+        assert!(check_turn(&board, &all, &rack, true).is_ok());
+
+        let moves = legal_moves(board.clone(), rack.clone(), MoveOptions::default()).unwrap().collect::<Vec<_>>();
+        assert_eq!(moves.len(), 2);
+        assert_eq!(moves[0].played, rack);
+        assert!(moves.iter().all(|turn| check_turn(&board, &turn.sets, &rack, true).is_ok()));
+    }
+}
@@ -0,0 +1,335 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::{Color, Piece};
+
+/// Pieces in the short notation, as in `[R1 R2 R3]`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Set(pub Vec<Piece>);
+
+/// Sets in the short notation, as in `[R1 R2 R3] [B5 O5 K5]`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Board(pub Vec<Vec<Piece>>);
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ParseErrorKind {
+    /// Colors are K(black), B(blue), R(red) and O(orange)
+    UnknownColor(char),
+    InvalidDomination(String),
+    Empty,
+    /// A `[` inside of a set
+    NestedSet,
+    /// A `]` without a `[` before it
+    UnopenedSet,
+    UnclosedSet,
+    /// A piece that isn't inside a set on a board
+    PieceOutsideSet(String),
+    /// More than one set where a single set was expected
+    MultipleSets,
+}
+
+/// What went wrong, and the byte offset in the input where it did
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub position: usize,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            ParseErrorKind::UnknownColor(c) =>
+                write!(f, "unknown color '{}', expected one of K, B, R, O or a J for a joker", c),
+            ParseErrorKind::InvalidDomination(d) =>
+                write!(f, "invalid domination '{}', expected a number from 1 to 13", d),
+            ParseErrorKind::Empty => write!(f, "expected a piece"),
+            ParseErrorKind::NestedSet => write!(f, "a set can't be inside of another set"),
+            ParseErrorKind::MultipleSets => write!(f, "expected a single set"),
+            ParseErrorKind::UnopenedSet => write!(f, "']' without a '[' before it"),
+            ParseErrorKind::UnclosedSet => write!(f, "'[' without a ']' after it"),
+            ParseErrorKind::PieceOutsideSet(p) => write!(f, "piece '{}' is not inside of a set", p)
+        }?;
+
+        write!(f, " at {}", self.position)
+    }
+}
+
+impl Error for ParseError {}
+
+fn error<T>(kind: ParseErrorKind, position: usize) -> Result<T, ParseError> {
+    Err(ParseError { kind, position })
+}
+
+impl Display for Color {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let letter = match self {
+            Color::Black => "K",
+            Color::Blue => "B",
+            Color::Red => "R",
+            Color::Orange => "O"
+        };
+
+        write!(f, "{}", letter)
+    }
+}
+
+impl FromStr for Color {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+
+        match (chars.next().map(|c| c.to_ascii_uppercase()), chars.next()) {
+            (Some('K'), None) => Ok(Color::Black),
+            (Some('B'), None) => Ok(Color::Blue),
+            (Some('R'), None) => Ok(Color::Red),
+            (Some('O'), None) => Ok(Color::Orange),
+            (None, _) => error(ParseErrorKind::Empty, 0),
+            (Some(c), _) => error(ParseErrorKind::UnknownColor(c), 0)
+        }
+    }
+}
+
+impl Display for Piece {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Piece::Joker => write!(f, "J"),
+            Piece::Normal(normal) => write!(f, "{}{}", normal.color, normal.domination),
+            Piece::MirrorJoker => write!(f, "JM"),
+            Piece::ColorChangeJoker => write!(f, "JC"),
+            Piece::DoubleJoker => write!(f, "JD")
+        }
+    }
+}
+
+impl FromStr for Piece {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        for joker in [Piece::Joker, Piece::MirrorJoker, Piece::ColorChangeJoker, Piece::DoubleJoker] {
+            if s.eq_ignore_ascii_case(&joker.to_string()) {
+                return Ok(joker);
+            }
+        }
+
+        let first = match s.chars().next() {
+            None => return error(ParseErrorKind::Empty, 0),
+            Some(first) => first
+        };
+        let color: Color = first.to_string().parse()?;
+        let domination = &s[first.len_utf8()..];
+
+        // Only the digits `Display` writes, so every piece has a single notation
+        let canonical = domination.bytes().all(|b| b.is_ascii_digit()) && !domination.starts_with('0');
+
+        match domination.parse::<u8>().ok().filter(|_| canonical).and_then(|d| Piece::try_normal(d, color).ok()) {
+            Some(piece) => Ok(piece),
+            None => error(ParseErrorKind::InvalidDomination(domination.to_string()), first.len_utf8())
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token<'a> {
+    Open,
+    Close,
+    Piece(&'a str),
+}
+
+// Splits the input into brackets and pieces, with the position of each
+fn tokens(s: &str) -> Vec<(usize, Token<'_>)> {
+    let mut result = Vec::new();
+    let mut start = None;
+
+    for (i, c) in s.char_indices() {
+        let separator = c.is_whitespace() || c == ',' || c == '[' || c == ']';
+
+        if separator {
+            if let Some(first) = start.take() {
+                result.push((first, Token::Piece(&s[first..i])));
+            }
+
+            if c == '[' {
+                result.push((i, Token::Open));
+            } else if c == ']' {
+                result.push((i, Token::Close));
+            }
+        } else if start.is_none() {
+            start = Some(i);
+        }
+    }
+
+    if let Some(first) = start {
+        result.push((first, Token::Piece(&s[first..])));
+    }
+
+    result
+}
+
+fn parse_piece(position: usize, s: &str) -> Result<Piece, ParseError> {
+    s.parse().map_err(|e: ParseError| ParseError { kind: e.kind, position: position + e.position })
+}
+
+fn parse_board(s: &str) -> Result<Vec<Vec<Piece>>, ParseError> {
+    let mut sets = Vec::new();
+    let mut current: Option<(usize, Vec<Piece>)> = None;
+
+    for (position, token) in tokens(s) {
+        match (token, current.as_mut()) {
+            (Token::Open, None) => current = Some((position, Vec::new())),
+            (Token::Open, Some(_)) => return error(ParseErrorKind::NestedSet, position),
+            (Token::Close, None) => return error(ParseErrorKind::UnopenedSet, position),
+            (Token::Close, Some(_)) => sets.push(current.take().unwrap().1),
+            (Token::Piece(p), None) => return error(ParseErrorKind::PieceOutsideSet(p.to_string()), position),
+            (Token::Piece(p), Some((_, set))) => set.push(parse_piece(position, p)?)
+        }
+    }
+
+    match current {
+        Some((position, _)) => error(ParseErrorKind::UnclosedSet, position),
+        None => Ok(sets)
+    }
+}
+
+impl Display for Set {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[")?;
+
+        for (i, piece) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+
+            write!(f, "{}", piece)?;
+        }
+
+        write!(f, "]")
+    }
+}
+
+/// The brackets around a set are optional, `R1 B13 O7 J` is a set of four pieces
+impl FromStr for Set {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim_start().starts_with('[') {
+            let mut sets = parse_board(s)?;
+
+            return match sets.len() {
+                1 => Ok(Set(sets.pop().unwrap())),
+                // The error points at the second set
+                _ => {
+                    let end = s.find(']').map_or(0, |i| i + 1);
+                    error(ParseErrorKind::MultipleSets, s[end..].find('[').map_or(end, |i| end + i))
+                }
+            };
+        }
+
+        let mut pieces = Vec::new();
+
+        for (position, token) in tokens(s) {
+            match token {
+                Token::Piece(p) => pieces.push(parse_piece(position, p)?),
+                Token::Open => return error(ParseErrorKind::NestedSet, position),
+                Token::Close => return error(ParseErrorKind::UnopenedSet, position)
+            }
+        }
+
+        Ok(Set(pieces))
+    }
+}
+
+impl Display for Board {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, set) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+
+            write!(f, "{}", Set(set.clone()))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Board {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Board(parse_board(s)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Color, Piece};
+    use crate::notation::{Board, ParseError, ParseErrorKind, Set};
+
+    #[test]
+    fn parse_pieces() {
+        assert_eq!("R1".parse(), Ok(Piece::normal(1, Color::Red)));
+        assert_eq!("b13".parse(), Ok(Piece::normal(13, Color::Blue)));
+        assert_eq!("K5".parse(), Ok(Piece::normal(5, Color::Black)));
+        assert_eq!("J".parse(), Ok(Piece::Joker));
+        assert_eq!("jd".parse(), Ok(Piece::DoubleJoker));
+        assert_eq!(Piece::MirrorJoker.to_string(), "JM");
+
+        assert_eq!("X1".parse::<Piece>(), Err(ParseError { kind: ParseErrorKind::UnknownColor('X'), position: 0 }));
+        assert_eq!("O14".parse::<Piece>(), Err(ParseError {
+            kind: ParseErrorKind::InvalidDomination("14".to_string()),
+            position: 1,
+        }));
+        assert_eq!("R".parse::<Piece>(), Err(ParseError {
+            kind: ParseErrorKind::InvalidDomination("".to_string()),
+            position: 1,
+        }));
+
+        for domination in ["+1", "01", "-1", " 1"] {
+            assert_eq!(format!("R{}", domination).parse::<Piece>(), Err(ParseError {
+                kind: ParseErrorKind::InvalidDomination(domination.to_string()),
+                position: 1,
+            }));
+        }
+    }
+
+    #[test]
+    fn parse_sets_and_boards() {
+        let set = vec!(Piece::normal(1, Color::Red), Piece::normal(13, Color::Blue), Piece::normal(7, Color::Orange), Piece::Joker);
+
+        assert_eq!("R1 B13 O7 J".parse(), Ok(Set(set.clone())));
+        assert_eq!("[R1, B13, O7, J]".parse(), Ok(Set(set.clone())));
+
+        assert_eq!("[R1 R2 R3] [B5 O5 K5]".parse(), Ok(Board(vec!(
+            vec!(Piece::normal(1, Color::Red), Piece::normal(2, Color::Red), Piece::normal(3, Color::Red)),
+            vec!(Piece::normal(5, Color::Blue), Piece::normal(5, Color::Orange), Piece::normal(5, Color::Black)),
+        ))));
+
+        assert_eq!("[R1 R2 Z3]".parse::<Board>(), Err(ParseError { kind: ParseErrorKind::UnknownColor('Z'), position: 7 }));
+        assert_eq!("[R1 R2".parse::<Board>(), Err(ParseError { kind: ParseErrorKind::UnclosedSet, position: 0 }));
+        assert_eq!("[R1] R2".parse::<Board>(), Err(ParseError {
+            kind: ParseErrorKind::PieceOutsideSet("R2".to_string()),
+            position: 5,
+        }));
+        assert_eq!("R1 ]".parse::<Set>(), Err(ParseError { kind: ParseErrorKind::UnopenedSet, position: 3 }));
+        assert_eq!("[R1 R2 R3][B1 B2 B3]".parse::<Set>(), Err(ParseError { kind: ParseErrorKind::MultipleSets, position: 10 }));
+        assert_eq!("[R1 [R2]]".parse::<Set>(), Err(ParseError { kind: ParseErrorKind::NestedSet, position: 4 }));
+    }
+
+    #[test]
+    fn round_trip_with_json() {
+        let board = vec!(
+            vec!(Piece::normal(1, Color::Red), Piece::normal(2, Color::Red), Piece::Joker),
+            vec!(Piece::normal(13, Color::Orange), Piece::normal(13, Color::Black), Piece::normal(13, Color::Blue)),
+        );
+        let text = Board(board.clone()).to_string();
+
+        assert_eq!(text, "[R1 R2 J] [O13 K13 B13]");
+        assert_eq!(text.parse(), Ok(Board(board.clone())));
+
+        let json = serde_json::to_string(&board).unwrap();
+        let from_json: Vec<Vec<Piece>> = serde_json::from_str(&json).unwrap();
+        assert_eq!(text.parse(), Ok(Board(from_json)));
+    }
+}
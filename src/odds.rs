@@ -0,0 +1,163 @@
+use std::rc::Rc;
+
+use crate::{Piece, RuleSet, RummikubError};
+use crate::catalogue::Catalogue;
+use crate::deck::TileBag;
+use crate::solve_tile::{Search, state};
+use crate::solve_turn::TurnGoal;
+
+/// What the next draws could do for a rack
+#[derive(Debug, PartialEq, Clone)]
+pub struct DrawOdds {
+    /// Pieces that aren't on the rack or the board, so they could be drawn
+    pub unseen: usize,
+    /// Every unseen piece that would let more pieces of the rack be in sets
+    pub outs: Vec<Piece>,
+    /// The chance at least one of the draws is one of the outs
+    pub chance: f64,
+    /// How many more pieces of the rack are expected to be in sets with a piece
+    /// drawn, every copy counting. Each of the outs is weighed on its own, by the
+    /// chance it's one of the draws.
+    pub expected_playable: f64,
+}
+
+// The chance none of `draws` pieces drawn out of `unseen` are one of `outs`
+fn none_drawn(unseen: usize, outs: usize, draws: usize) -> f64 {
+    (0..draws.min(unseen)).map(|i| {
+        if i + outs >= unseen { 0.0 } else { (unseen - outs - i) as f64 / (unseen - i) as f64 }
+    }).product()
+}
+
+pub fn draw_odds(board: &[Vec<Piece>], rack: &[Piece], draws: usize) -> Result<DrawOdds, RummikubError> {
+    draw_odds_with_rules(board, rack, draws, &RuleSet::default())
+}
+
+/// The odds of the next `draws` pieces drawn helping the rack. Every piece the
+/// player can't see is as likely to be drawn, whether it's in the pool or on the
+/// rack of another player. Only sets made of a single drawn piece and pieces of
+/// the rack count, the board only tells which pieces are seen.
+pub fn draw_odds_with_rules(board: &[Vec<Piece>], rack: &[Piece], draws: usize, rules: &RuleSet) -> Result<DrawOdds, RummikubError> {
+    rules.check()?;
+
+    let seen: Vec<Piece> = board.iter().flatten().chain(rack).copied().collect();
+    let mut bag = TileBag::for_rules(rules);
+    bag.check(&seen)?;
+
+    for piece in &seen {
+        bag.remove(piece);
+    }
+
+    let catalogue = Catalogue::cached(rules.jokers as usize, rules.special_jokers, rules);
+
+    // The most pieces of the rack that can be in sets, before and after a draw
+    let mut search = Search::new(Rc::clone(&catalogue), TurnGoal::MostTiles);
+    let mut state = state(&[], rack, rules);
+    let most = search.best(&mut state).unwrap_or(0);
+
+    let unseen = bag.pieces();
+    let mut outs = Vec::new();
+    let mut expected_playable = 0.0;
+
+    for drawn in 0..rules.kinds() {
+        let copies = unseen.iter().filter(|piece| rules.kind(piece) == Some(drawn)).count();
+
+        if copies == 0 {
+            continue;
+        }
+
+        // The drawn piece has to be in a set, and only the pieces of the rack score
+        state[drawn] += 1;
+        let gained = search.best(&mut state).map_or(0, |placed| placed.saturating_sub(most));
+        state[drawn] -= 1;
+
+        if gained == 0 {
+            continue;
+        }
+
+        outs.extend((0..copies).map(|_| rules.piece(drawn)));
+        expected_playable += gained as f64 * (1.0 - none_drawn(unseen.len(), copies, draws));
+    }
+
+    Ok(DrawOdds {
+        unseen: unseen.len(),
+        chance: 1.0 - none_drawn(unseen.len(), outs.len(), draws),
+        outs,
+        expected_playable,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Color, Piece, RummikubError};
+    use crate::odds::draw_odds;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn count_the_pieces_completing_a_set() {
+        let board = vec!(vec!(Piece::normal(2, Color::Red), Piece::normal(3, Color::Red), Piece::normal(4, Color::Red)));
+        let rack = vec!(Piece::normal(5, Color::Red), Piece::normal(6, Color::Red), Piece::normal(11, Color::Blue));
+
+        let odds = draw_odds(&board, &rack, 1).unwrap();
+        assert_eq!(odds.unseen, 100);
+        assert_eq!(odds.outs, vec!(
+            Piece::normal(4, Color::Red),
+            Piece::normal(7, Color::Red),
+            Piece::normal(7, Color::Red),
+            Piece::Joker,
+            Piece::Joker,
+        ));
+        assert!(close(odds.chance, 0.05));
+        assert!(close(odds.expected_playable, 2.0 * 0.05));
+
+        let two = draw_odds(&board, &rack, 2).unwrap();
+        assert!(close(two.chance, 1.0 - (95.0 / 100.0) * (94.0 / 99.0)));
+    }
+
+    #[test]
+    fn count_every_copy_played() {
+        let rack = vec!(
+            Piece::normal(5, Color::Red),
+            Piece::normal(6, Color::Red),
+            Piece::normal(7, Color::Red),
+            Piece::normal(5, Color::Red),
+            Piece::normal(5, Color::Blue),
+        );
+
+        // The second red 5 and the blue 5 make a group with a black or orange 5, or a joker
+        let odds = draw_odds(&[], &rack, 1).unwrap();
+        assert_eq!(odds.outs.len(), 6);
+        assert!(close(odds.expected_playable, 6.0 * 2.0 / 101.0));
+    }
+
+    #[test]
+    fn only_count_pieces_not_in_a_set_yet() {
+        let rack = vec!(
+            Piece::normal(9, Color::Red),
+            Piece::normal(9, Color::Blue),
+            Piece::normal(9, Color::Black),
+            Piece::normal(1, Color::Orange),
+        );
+
+        // The nines are already a set, so the orange 9 doesn't play any more of the
+        // rack, and the orange 1 can't be in a set with a single piece drawn
+        let odds = draw_odds(&[], &rack, 3).unwrap();
+        assert_eq!(odds.outs, vec!());
+        assert!(close(odds.expected_playable, 0.0));
+        assert!(close(draw_odds(&[], &rack, 200).unwrap().chance, 0.0));
+
+        // The orange 2 or a joker puts the orange 1 and 3 in a set
+        let mut rack = rack;
+        rack.push(Piece::normal(3, Color::Orange));
+        let odds = draw_odds(&[], &rack, 1).unwrap();
+        assert_eq!(odds.outs, vec!(Piece::normal(2, Color::Orange), Piece::normal(2, Color::Orange), Piece::Joker, Piece::Joker));
+
+        // Without a rack, nothing is expected to be played
+        assert_eq!(draw_odds(&[], &[], 1).unwrap().expected_playable.to_bits(), 0.0f64.to_bits());
+
+        let copies = vec!(Piece::Joker, Piece::Joker, Piece::Joker);
+        assert_eq!(draw_odds(&[], &copies, 1).err(), Some(RummikubError::TooManyCopies { piece: Piece::Joker, count: 3, max: 2 }));
+    }
+}
@@ -0,0 +1,230 @@
+use crate::{NormalPiece, Piece, RuleSet, RummikubError, Wrap};
+use crate::deck::TileBag;
+use crate::solve_tile::solve_board_with_rules;
+use crate::solve_turn::{solve_turn_with_rules, TurnGoal};
+use crate::validate::valid_set_with_rules;
+
+/// Tiles a hand has to have to win, after the last discard
+pub const OKEY_HAND_SIZE: usize = 14;
+
+/// Okey is played with the same 106 tiles, where `Piece::Joker` is one of the two
+/// false jokers. The tile after the indicator is the okey, which is wild, and the
+/// false jokers stand for it.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Okey {
+    indicator: NormalPiece,
+}
+
+/// What's left of the hand after discarding a piece
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Discard {
+    pub piece: Piece,
+    pub wins: bool,
+    /// Pieces of the rest of the hand that can be arranged into sets
+    pub melded: usize,
+}
+
+impl Okey {
+    /// The indicator has to be a normal piece
+    pub fn new(indicator: Piece) -> Result<Self, RummikubError> {
+        let rules = Okey::rules();
+        rules.check_piece(&indicator)?;
+
+        match indicator {
+            Piece::Normal(indicator) => Ok(Okey { indicator }),
+            _ => Err(RummikubError::InvalidTile(indicator))
+        }
+    }
+
+    /// The standard tiles, where 1 can follow 13 only at the end of a run
+    pub fn rules() -> RuleSet {
+        RuleSet { wrap: Wrap::LowestAfterHighest, ..RuleSet::default() }
+    }
+
+    /// The piece of the same color one above the indicator, 1 comes after 13
+    pub fn okey(&self) -> NormalPiece {
+        let rules = Okey::rules();
+        let domination = if self.indicator.domination == rules.highest_domination {
+            rules.lowest_domination
+        } else {
+            self.indicator.domination + 1
+        };
+
+        NormalPiece { domination, color: self.indicator.color }
+    }
+
+    /// The pieces as the solver sees them, okeys become jokers and false jokers
+    /// become the okey
+    pub fn resolve(&self, pieces: &[Piece]) -> Vec<Piece> {
+        let okey = self.okey();
+
+        pieces.iter().map(|piece| match piece {
+            Piece::Joker => Piece::Normal(okey),
+            Piece::Normal(normal) if *normal == okey => Piece::Joker,
+            _ => *piece
+        }).collect()
+    }
+
+    pub fn valid_set(&self, pieces: &[Piece]) -> bool {
+        valid_set_with_rules(&self.resolve(pieces), &Okey::rules())
+    }
+
+    /// Seven pairs of identical pieces, an okey can pair up with any piece
+    pub fn seven_pairs(&self, hand: &[Piece]) -> bool {
+        if hand.len() != OKEY_HAND_SIZE {
+            return false;
+        }
+
+        let resolved = self.resolve(hand);
+        let wild = resolved.iter().filter(|p| **p == Piece::Joker).count();
+        let mut singles = 0;
+
+        for (i, piece) in resolved.iter().enumerate() {
+            if *piece != Piece::Joker && !resolved[..i].contains(piece) {
+                singles += resolved.iter().filter(|p| *p == piece).count() % 2;
+            }
+        }
+
+        // Okeys left over after the singles can pair up with each other
+        singles <= wild && (wild - singles) % 2 == 0
+    }
+
+    /// A hand wins if all 14 pieces can be arranged into sets, or into seven pairs
+    pub fn is_winning(&self, hand: &[Piece]) -> Result<bool, RummikubError> {
+        let rules = Okey::rules();
+        TileBag::for_rules(&rules).check(hand)?;
+
+        if hand.len() != OKEY_HAND_SIZE {
+            return Ok(false);
+        }
+
+        Ok(self.seven_pairs(hand) || solve_board_with_rules(self.resolve(hand), &rules)?.is_some())
+    }
+
+    /// The piece to discard, preferring a discard that wins and otherwise the one
+    /// leaving the most pieces in sets. An okey is only discarded when nothing else
+    /// does as well. Returns `None` for an empty hand.
+    pub fn best_discard(&self, hand: &[Piece]) -> Result<Option<Discard>, RummikubError> {
+        let rules = Okey::rules();
+        TileBag::for_rules(&rules).check(hand)?;
+
+        let okey = Piece::Normal(self.okey());
+        let mut best: Option<Discard> = None;
+
+        for (i, piece) in hand.iter().enumerate() {
+            if hand[..i].contains(piece) {
+                continue;
+            }
+
+            let mut rest = hand.to_vec();
+            rest.remove(i);
+
+            let melded = match solve_turn_with_rules(vec!(), self.resolve(&rest), TurnGoal::MostTiles, &rules)? {
+                None => 0,
+                Some(turn) => turn.played.len()
+            };
+            let wins = rest.len() == OKEY_HAND_SIZE && (melded == rest.len() || self.seven_pairs(&rest));
+            let discard = Discard { piece: *piece, wins, melded };
+
+            let better = match best {
+                None => true,
+                Some(b) => (wins, melded, *piece != okey) > (b.wins, b.melded, b.piece != okey)
+            };
+
+            if better {
+                best = Some(discard);
+            }
+        }
+
+        Ok(best)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Color, NormalPiece, Piece, RummikubError};
+    use crate::okey::{Discard, Okey};
+
+    fn hand(pieces: &[(u8, Color)]) -> Vec<Piece> {
+        pieces.iter().map(|(d, c)| Piece::normal(*d, *c)).collect()
+    }
+
+    #[test]
+    fn find_the_okey_from_the_indicator() {
+        assert_eq!(Okey::new(Piece::normal(5, Color::Red)).unwrap().okey(), NormalPiece { domination: 6, color: Color::Red });
+        assert_eq!(Okey::new(Piece::normal(13, Color::Blue)).unwrap().okey(), NormalPiece { domination: 1, color: Color::Blue });
+        assert_eq!(Okey::new(Piece::Joker), Err(RummikubError::InvalidTile(Piece::Joker)));
+
+        let okey = Okey::new(Piece::normal(5, Color::Red)).unwrap();
+        assert_eq!(okey.resolve(&[Piece::Joker, Piece::normal(6, Color::Red)]), vec!(Piece::normal(6, Color::Red), Piece::Joker));
+
+        // The false joker is the red 6, and the okey can be anything
+        assert!(okey.valid_set(&[Piece::normal(4, Color::Red), Piece::normal(5, Color::Red), Piece::Joker]));
+        assert!(okey.valid_set(&[Piece::normal(9, Color::Blue), Piece::normal(6, Color::Red), Piece::normal(9, Color::Black)]));
+        assert!(!okey.valid_set(&[Piece::normal(4, Color::Blue), Piece::normal(5, Color::Blue), Piece::Joker]));
+    }
+
+    #[test]
+    fn win_with_sets() {
+        let okey = Okey::new(Piece::normal(5, Color::Red)).unwrap();
+        let mut winning = hand(&[
+            (1, Color::Black), (2, Color::Black), (3, Color::Black), (6, Color::Red),
+            (7, Color::Blue), (7, Color::Red), (7, Color::Orange), (7, Color::Black),
+            (12, Color::Orange), (13, Color::Orange), (1, Color::Orange),
+            (4, Color::Red), (5, Color::Red),
+        ]);
+        winning.push(Piece::Joker);
+
+        assert_eq!(okey.is_winning(&winning), Ok(true));
+        assert_eq!(okey.is_winning(&winning[1..]), Ok(false));
+
+        // A blue 1 doesn't end the orange run
+        winning[10] = Piece::normal(1, Color::Blue);
+        assert_eq!(okey.is_winning(&winning), Ok(false));
+    }
+
+    #[test]
+    fn win_with_seven_pairs() {
+        let okey = Okey::new(Piece::normal(5, Color::Red)).unwrap();
+        let pairs = hand(&[
+            (1, Color::Black), (1, Color::Black), (2, Color::Blue), (2, Color::Blue),
+            (3, Color::Red), (3, Color::Red), (4, Color::Orange), (4, Color::Orange),
+            (5, Color::Black), (5, Color::Black), (8, Color::Blue), (8, Color::Blue),
+            (9, Color::Orange), (6, Color::Red),
+        ]);
+
+        assert!(okey.seven_pairs(&pairs));
+        assert_eq!(okey.is_winning(&pairs), Ok(true));
+
+        let mut broken = pairs.clone();
+        broken[13] = Piece::normal(10, Color::Orange);
+        assert!(!okey.seven_pairs(&broken));
+    }
+
+    #[test]
+    fn discard_the_piece_that_wins() {
+        let okey = Okey::new(Piece::normal(5, Color::Red)).unwrap();
+        let mut pieces = hand(&[
+            (1, Color::Black), (2, Color::Black), (3, Color::Black), (6, Color::Red),
+            (7, Color::Blue), (11, Color::Blue), (7, Color::Red), (7, Color::Orange), (7, Color::Black),
+            (12, Color::Orange), (13, Color::Orange), (1, Color::Orange),
+            (4, Color::Red), (5, Color::Red),
+        ]);
+        pieces.push(Piece::Joker);
+
+        assert_eq!(okey.best_discard(&pieces), Ok(Some(Discard {
+            piece: Piece::normal(11, Color::Blue),
+            wins: true,
+            melded: 14,
+        })));
+
+        // Nothing wins, the blue 11 and the orange 4 fit nowhere and the first of them goes
+        pieces[11] = Piece::normal(4, Color::Orange);
+        let discard = okey.best_discard(&pieces).unwrap().unwrap();
+        assert!(!discard.wins);
+        assert_eq!(discard.melded, 13);
+        assert_eq!(discard.piece, Piece::normal(11, Color::Blue));
+
+        assert_eq!(okey.best_discard(&[]), Ok(None));
+    }
+}
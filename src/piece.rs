@@ -1,6 +1,8 @@
 use std::slice::Iter;
 use serde::{Serialize,Deserialize};
 
+use crate::{RuleSet, RummikubError};
+
 #[derive(Debug, PartialEq, Copy, Clone, Eq, Ord, PartialOrd, Serialize, Deserialize)]
 pub enum Color {
     Black,
@@ -9,9 +11,10 @@ pub enum Color {
     Orange
 }
 
+pub(crate) static COLORS: [Color; 4] = [Color::Black, Color::Blue, Color::Red, Color::Orange];
+
 impl Color {
     pub fn iterator() -> Iter<'static, Color> {
-        static COLORS: [Color; 4] = [Color::Black, Color::Blue, Color::Red, Color::Orange];
         COLORS.iter()
     }
 }
@@ -26,22 +29,64 @@ pub struct NormalPiece {
 #[serde(tag = "type")]
 pub enum Piece {
     Joker,
-    Normal(NormalPiece)
+    Normal(NormalPiece),
+    /// Stands in the middle of a set, the pieces after it are the ones before it in reverse
+    MirrorJoker,
+    /// Stands for a piece of a run, where the color of the run can change
+    ColorChangeJoker,
+    /// Stands for two pieces of a run or a group
+    DoubleJoker,
 }
 
+/// Penalty value of a joker left on a rack at the end of a round
+pub const JOKER_VALUE: u8 = 30;
+
 impl Piece {
+    /// Panics on a domination outside of 1 to 13, 0 included, where `try_normal`
+    /// returns `RummikubError::InvalidTile` instead.
+    /// Pieces for other rules can be made with `Piece::Normal` directly.
     pub fn normal(domination: u8, color:Color) -> Self {
-        if domination > 13 {
-            panic!("got domination of more than 13: {}", domination);
+        match Piece::try_normal(domination, color) {
+            Ok(piece) => piece,
+            Err(_) => panic!("got domination outside of 1 to 13: {}", domination)
+        }
+    }
+
+    pub fn try_normal(domination: u8, color:Color) -> Result<Self, RummikubError> {
+        let piece = Piece::Normal(NormalPiece{ domination, color });
+
+        piece.check()?;
+        Ok(piece)
+    }
+
+    /// Pieces that were deserialized never went through `try_normal`, so they
+    /// might be invalid
+    pub fn check(&self) -> Result<(), RummikubError> {
+        self.check_with_rules(&RuleSet::default())
+    }
+
+    /// Same as `check`, for a game played by other rules
+    pub fn check_with_rules(&self, rules: &RuleSet) -> Result<(), RummikubError> {
+        rules.check_piece(self)
+    }
+
+    /// Point value of the piece, a joker is worth its penalty value
+    pub fn value(&self) -> u8 {
+        match self {
+            Piece::Normal(normal) => normal.domination,
+            _ => JOKER_VALUE
         }
+    }
 
-        return Piece::Normal(NormalPiece{ domination, color })
+    /// The jokers of the deluxe editions, only played with `RuleSet::special_jokers`
+    pub fn is_special_joker(&self) -> bool {
+        matches!(self, Piece::MirrorJoker | Piece::ColorChangeJoker | Piece::DoubleJoker)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{Color, Piece};
+    use crate::{Color, NormalPiece, Piece, RuleSet, RummikubError};
 
     #[test]
     fn parse_json_table() {
@@ -55,4 +100,21 @@ mod tests {
 
         assert_eq!(v, vec!(Piece::Joker, Piece::normal(1, Color::Red)));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn reject_invalid_dominations() {
+        assert_eq!(Piece::try_normal(13, Color::Blue), Ok(Piece::normal(13, Color::Blue)));
+        assert_eq!(
+            Piece::try_normal(0, Color::Blue),
+            Err(RummikubError::InvalidTile(Piece::Normal(NormalPiece { domination: 0, color: Color::Blue })))
+        );
+        assert!(Piece::try_normal(14, Color::Blue).is_err());
+
+        let v: Vec<Piece> = serde_json::from_str(r#"[{"type":"Normal", "domination":20, "color":"Red"}]"#).unwrap();
+        assert!(v[0].check().is_err());
+
+        let rules = RuleSet { highest_domination: 20, ..RuleSet::default() };
+        assert_eq!(v[0].check_with_rules(&rules), Ok(()));
+        assert!(Piece::DoubleJoker.check_with_rules(&rules).is_err());
+    }
+}
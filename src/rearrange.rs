@@ -0,0 +1,306 @@
+use std::rc::Rc;
+
+use crate::{Piece, RuleSet, RummikubError};
+use crate::deck::TileBag;
+use crate::solve_tile::{join_runs, prepare, Search};
+use crate::solve_turn::TurnGoal;
+use crate::sort_set::sort_sets;
+use crate::validate::valid_set;
+
+/// What happened to the sets of the board, `original` is an index into the board
+/// given and `set` an index into the sets of the rearrangement
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum SetChange {
+    /// The set was left as it is
+    Kept { original: usize, set: usize },
+    /// All the pieces of the set stayed together, with added pieces joining them
+    Extended { original: usize, set: usize },
+    /// The pieces of the set were spread over more than one new set
+    Split { original: usize, sets: Vec<usize> },
+    /// The new set was made of pieces from more than one set of the board
+    Merged { originals: Vec<usize>, set: usize },
+    /// The new set was made only of added pieces
+    Added { set: usize },
+}
+
+/// A board with the added pieces placed, disturbing the fewest sets possible
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Rearrangement {
+    /// The sets kept first, in the order of the board, and then the new sets
+    pub sets: Vec<Vec<Piece>>,
+    pub changes: Vec<SetChange>,
+    /// Pieces of the board that had to leave the rest of their set behind
+    pub moved: usize,
+}
+
+// Works out where the pieces of every new set came from
+fn diff(board: &[Vec<Piece>], changed: &[usize], new_sets: Vec<Vec<Piece>>) -> Rearrangement {
+    let mut sets = Vec::new();
+    let mut changes = Vec::new();
+
+    for (original, set) in board.iter().enumerate() {
+        if !changed.contains(&original) {
+            changes.push(SetChange::Kept { original, set: sets.len() });
+            sets.push(set.clone());
+        }
+    }
+
+    let first_new = sets.len();
+
+    // The pieces of every changed set not yet found in a new set
+    let mut left: Vec<Vec<Piece>> = changed.iter().map(|i| board[*i].clone()).collect();
+    // How many pieces of every changed set went to each new set
+    let mut received: Vec<Vec<(usize, usize)>> = vec!(Vec::new(); changed.len());
+    let mut sources: Vec<Vec<usize>> = Vec::new();
+    let mut with_added: Vec<bool> = Vec::new();
+
+    for (n, set) in new_sets.iter().enumerate() {
+        let mut from: Vec<usize> = Vec::new();
+        let mut added = false;
+
+        for piece in set {
+            // Prefer a set this new set already took pieces from, so identical
+            // pieces don't make up splits that didn't happen
+            let source = from.iter().copied().find(|c| left[*c].contains(piece))
+                .or_else(|| (0..left.len()).find(|c| left[*c].contains(piece)));
+
+            match source {
+                None => added = true,
+                Some(c) => {
+                    let position = left[c].iter().position(|p| p == piece).unwrap();
+                    left[c].remove(position);
+
+                    match received[c].iter_mut().find(|(set, _)| *set == first_new + n) {
+                        Some((_, count)) => *count += 1,
+                        None => received[c].push((first_new + n, 1))
+                    }
+
+                    if !from.contains(&c) {
+                        from.push(c);
+                    }
+                }
+            }
+        }
+
+        sources.push(from);
+        with_added.push(added);
+        sets.push(set.clone());
+    }
+
+    let mut moved = 0;
+
+    for (c, original) in changed.iter().enumerate() {
+        // The largest part of a set stays where it was, the rest are moved
+        let stayed = received[c].iter().map(|(_, count)| *count).max().unwrap_or(0);
+        moved += board[*original].len() - stayed;
+
+        if received[c].len() > 1 {
+            changes.push(SetChange::Split {
+                original: *original,
+                sets: received[c].iter().map(|(set, _)| *set).collect(),
+            });
+        }
+    }
+
+    for (n, from) in sources.iter().enumerate() {
+        let set = first_new + n;
+
+        match from.len() {
+            0 => changes.push(SetChange::Added { set }),
+            1 => {
+                if received[from[0]].len() == 1 && with_added[n] {
+                    changes.push(SetChange::Extended { original: changed[from[0]], set });
+                }
+            }
+            _ => changes.push(SetChange::Merged {
+                originals: from.iter().map(|c| changed[*c]).collect(),
+                set,
+            })
+        }
+    }
+
+    Rearrangement { sets, changes, moved }
+}
+
+/// Places all the added pieces on the board, changing as few of its sets as possible.
+/// Returns `None` if the pieces can't be placed even by rearranging the whole board.
+pub fn rearrange(board: Vec<Vec<Piece>>, added: Vec<Piece>) -> Result<Option<Rearrangement>, RummikubError> {
+    if !board.iter().all(valid_set) {
+        return Err(RummikubError::InvalidSet);
+    }
+
+    let mut all: Vec<Piece> = board.iter().flatten().copied().collect();
+    all.extend(&added);
+    let rules = RuleSet::default();
+    TileBag::for_rules(&rules).check(&all)?;
+
+    let (everything, catalogue) = prepare(&all, &[], &rules);
+    // Every set of the board that is left as it is scores a point, as many times
+    // as the board has the set
+    let kinds: Vec<Vec<(usize, u8)>> = board.iter().map(|set| rules.kind_counts(set).into_iter()
+        .enumerate()
+        .filter(|(_, count)| *count > 0)
+        .collect()
+    ).collect();
+
+    let mut search = Search::new(Rc::clone(&catalogue), TurnGoal::MostTiles);
+
+    for (i, set) in catalogue.sets.iter().enumerate() {
+        let times = kinds.iter().filter(|kinds| **kinds == set.kinds).count();
+        // The most times the set could be made of the pieces
+        let most = set.kinds.iter().map(|(k, count)| (everything[*k] / count) as usize).min().unwrap_or(0);
+
+        if times > 0 {
+            search.reward(i, 1, Some(times as u8).filter(|_| most > times));
+        }
+    }
+
+    let found = match search.cover(search.rewarded(everything)) {
+        None => return Ok(None),
+        Some(found) => found
+    };
+
+    // The sets of the board found in the cover were kept, the others changed
+    let mut new_sets = found.sets;
+    let mut changed = Vec::new();
+
+    for (original, set) in board.iter().enumerate() {
+        let counts = rules.kind_counts(set);
+
+        match new_sets.iter().position(|new_set| rules.kind_counts(new_set) == counts) {
+            Some(position) => {
+                new_sets.remove(position);
+            }
+            _ => changed.push(original)
+        }
+    }
+
+    let new_sets = sort_sets(join_runs(new_sets, &rules), &rules)?;
+
+    Ok(Some(diff(&board, &changed, new_sets)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Color, Piece, RummikubError};
+    use crate::rearrange::{rearrange, Rearrangement, SetChange};
+
+    #[test]
+    fn keep_sets_that_dont_need_to_change() {
+        let group = vec!(
+            Piece::normal(5, Color::Black),
+            Piece::normal(5, Color::Blue),
+            Piece::normal(5, Color::Orange),
+        );
+
+        assert_eq!(rearrange(
+            vec!(group.clone(), vec!(
+                Piece::normal(1, Color::Red),
+                Piece::normal(2, Color::Red),
+                Piece::normal(3, Color::Red),
+            )),
+            vec!(Piece::normal(4, Color::Red)),
+        ), Ok(Some(Rearrangement {
+            sets: vec!(group, vec!(
+                Piece::normal(1, Color::Red),
+                Piece::normal(2, Color::Red),
+                Piece::normal(3, Color::Red),
+                Piece::normal(4, Color::Red),
+            )),
+            changes: vec!(
+                SetChange::Kept { original: 0, set: 0 },
+                SetChange::Extended { original: 1, set: 1 },
+            ),
+            moved: 0,
+        })));
+    }
+
+    #[test]
+    fn describe_split_and_merged_sets() {
+        let result = rearrange(
+            vec!(
+                vec!(
+                    Piece::normal(1, Color::Red),
+                    Piece::normal(2, Color::Red),
+                    Piece::normal(3, Color::Red),
+                    Piece::normal(4, Color::Red),
+                ),
+                vec!(
+                    Piece::normal(4, Color::Blue),
+                    Piece::normal(5, Color::Blue),
+                    Piece::normal(6, Color::Blue),
+                ),
+            ),
+            vec!(Piece::normal(4, Color::Orange), Piece::normal(7, Color::Blue)),
+        ).unwrap().unwrap();
+
+        assert_eq!(result.sets, vec!(
+            vec!(
+                Piece::normal(1, Color::Red),
+                Piece::normal(2, Color::Red),
+                Piece::normal(3, Color::Red),
+            ),
+            vec!(
+                Piece::normal(4, Color::Blue),
+                Piece::normal(4, Color::Red),
+                Piece::normal(4, Color::Orange),
+            ),
+            vec!(
+                Piece::normal(5, Color::Blue),
+                Piece::normal(6, Color::Blue),
+                Piece::normal(7, Color::Blue),
+            ),
+        ));
+
+        assert_eq!(result.changes, vec!(
+            SetChange::Split { original: 0, sets: vec!(0, 1) },
+            SetChange::Split { original: 1, sets: vec!(1, 2) },
+            SetChange::Merged { originals: vec!(1, 0), set: 1 },
+        ));
+
+        assert_eq!(result.moved, 2);
+    }
+
+    #[test]
+    fn keep_most_of_a_large_board() {
+        let mut board = Vec::new();
+
+        for domination in 1..14 {
+            let group = vec!(
+                Piece::normal(domination, Color::Black),
+                Piece::normal(domination, Color::Blue),
+                Piece::normal(domination, Color::Orange),
+            );
+            board.push(group.clone());
+            board.push(group);
+        }
+
+        let added: Vec<Piece> = [1, 4, 7, 10, 13].iter().map(|d| Piece::normal(*d, Color::Red)).collect();
+        let result = rearrange(board, added).unwrap().unwrap();
+
+        assert_eq!(result.sets.len(), 26);
+        assert_eq!(result.changes.iter().filter(|c| matches!(c, SetChange::Kept { .. })).count(), 21);
+        assert_eq!(result.changes.iter().filter(|c| matches!(c, SetChange::Extended { .. })).count(), 5);
+        assert_eq!(result.moved, 0);
+    }
+
+    #[test]
+    fn reject_invalid_board_sets() {
+        assert_eq!(rearrange(
+            vec!(vec!(Piece::normal(1, Color::Red), Piece::normal(2, Color::Red))),
+            vec!(Piece::normal(3, Color::Red)),
+        ), Err(RummikubError::InvalidSet));
+    }
+
+    #[test]
+    fn fail_when_pieces_cant_be_placed() {
+        assert_eq!(rearrange(
+            vec!(vec!(
+                Piece::normal(1, Color::Red),
+                Piece::normal(2, Color::Red),
+                Piece::normal(3, Color::Red),
+            )),
+            vec!(Piece::normal(9, Color::Blue)),
+        ), Ok(None));
+    }
+}
@@ -0,0 +1,246 @@
+use itertools::Itertools;
+
+use crate::{NormalPiece, Piece, RuleSet, RummikubError};
+use crate::meld::INITIAL_MELD_POINTS;
+use crate::sort_set::{effective_dominations, resolve_jokers_with_rules};
+use crate::validate::valid_set_with_rules;
+
+// Every piece a joker of the set could stand for, for each joker of the set
+pub(crate) fn joker_options(set: &[Piece], rules: &RuleSet) -> Vec<Vec<NormalPiece>> {
+    match resolve_jokers_with_rules(set, rules) {
+        Err(_) => vec!(),
+        Ok(tiles) => tiles.into_iter()
+            .filter(|tile| tile.piece == Piece::Joker)
+            .map(|tile| tile.options.concat())
+            .collect()
+    }
+}
+
+fn sorted(set: &[Piece], rules: &RuleSet) -> Vec<Piece> {
+    let mut pieces = set.to_vec();
+    pieces.sort_by_key(|piece| rules.kind(piece));
+    pieces
+}
+
+// Every way to count the pieces from the rack in each set after the turn. A piece
+// both on the board and the rack could have come from either, whatever the order
+// of the sets. Only the sets with jokers are counted, the rest don't matter.
+fn rack_attributions(after: &[Vec<Piece>], played: &[Piece], after_options: &[Vec<Vec<NormalPiece>>], rules: &RuleSet) -> Vec<Vec<usize>> {
+    rules.kind_counts(played).iter().enumerate()
+        .filter(|(_, count)| **count > 0)
+        .map(|(kind, count)| {
+            let holders: Vec<usize> = after.iter().enumerate()
+                .flat_map(|(i, set)| set.iter().filter(|piece| rules.kind(piece) == Some(kind)).map(move |_| i))
+                .collect();
+
+            holders.into_iter().combinations(*count as usize)
+                .map(|chosen| {
+                    let mut from_rack = vec!(0; after.len());
+
+                    for set in chosen.into_iter().filter(|set| !after_options[*set].is_empty()) {
+                        from_rack[set] += 1;
+                    }
+
+                    from_rack
+                })
+                .unique()
+                .collect::<Vec<Vec<usize>>>()
+        })
+        .multi_cartesian_product()
+        .map(|per_kind| (0..after.len()).map(|set| per_kind.iter().map(|from_rack| from_rack[set]).sum()).collect())
+        .unique()
+        .collect()
+}
+
+pub fn check_turn(before: &[Vec<Piece>], after: &[Vec<Piece>], rack: &[Piece], melded: bool) -> Result<Vec<Piece>, RummikubError> {
+    check_turn_with_rules(before, after, rack, melded, &RuleSet::default())
+}
+
+/// Checks the board after a turn can follow the board before it, returning the
+/// pieces the player played from their rack. `melded` is whether the player
+/// made their initial meld on an earlier turn.
+///
+/// Every piece of the board has to stay on it, only pieces of the rack can be
+/// added and every set has to be valid. Before the initial meld, the sets on the
+/// board can't be changed and the new sets have to be worth `INITIAL_MELD_POINTS`.
+/// A joker that no longer stands for the same piece was retrieved, and has to be
+/// played again in a set with at least two pieces from the rack.
+pub fn check_turn_with_rules(
+    before: &[Vec<Piece>],
+    after: &[Vec<Piece>],
+    rack: &[Piece],
+    melded: bool,
+    rules: &RuleSet,
+) -> Result<Vec<Piece>, RummikubError> {
+    if let Some(set) = after.iter().position(|set| !valid_set_with_rules(set, rules)) {
+        return Err(RummikubError::InvalidBoardSet(set));
+    }
+
+    let mut left_on_board: Vec<Piece> = before.concat();
+    let mut left_on_rack = rack.to_vec();
+    let mut played = Vec::new();
+
+    for piece in after.iter().flatten() {
+        if let Some(position) = left_on_board.iter().position(|p| p == piece) {
+            left_on_board.remove(position);
+        } else if let Some(position) = left_on_rack.iter().position(|p| p == piece) {
+            played.push(left_on_rack.remove(position));
+        } else {
+            return Err(RummikubError::NotOnRack(*piece));
+        }
+    }
+
+    if let Some(piece) = left_on_board.first() {
+        return Err(RummikubError::TakenFromBoard(*piece));
+    }
+
+    if played.is_empty() {
+        return Err(RummikubError::NothingPlayed);
+    }
+
+    if !melded {
+        let mut new_sets: Vec<Vec<Piece>> = after.iter().map(|set| sorted(set, rules)).collect();
+
+        // The sets already on the board have to stay as they are
+        for set in before {
+            let position = new_sets.iter().position(|new_set| *new_set == sorted(set, rules))
+                .ok_or(RummikubError::BoardChangedBeforeMeld)?;
+
+            new_sets.remove(position);
+        }
+
+        let points: u32 = new_sets.iter()
+            .flat_map(|set| effective_dominations(set, rules))
+            .map(|d| d as u32)
+            .sum();
+
+        if points < INITIAL_MELD_POINTS {
+            return Err(RummikubError::InitialMeldTooLow { points, required: INITIAL_MELD_POINTS });
+        }
+    }
+
+    let before_jokers: Vec<Vec<NormalPiece>> = before.iter().flat_map(|set| joker_options(set, rules)).collect();
+
+    if before_jokers.is_empty() {
+        return Ok(played);
+    }
+
+    let after_options: Vec<Vec<Vec<NormalPiece>>> = after.iter().map(|set| joker_options(set, rules)).collect();
+
+    // Every joker of the board either still stands for the same piece, or was
+    // played again along with pieces from the rack
+    let accounted = rack_attributions(after, &played, &after_options, rules).iter().any(|from_rack| {
+        let after_jokers: Vec<(&Vec<NormalPiece>, bool)> = after_options.iter().zip(from_rack)
+            .flat_map(|(options, count)| options.iter().map(move |options| (options, *count >= 2)))
+            .collect();
+
+        after_jokers.iter().permutations(before_jokers.len()).any(|assignment| {
+            before_jokers.iter().zip(assignment).all(|(before, (after, replayed))| {
+                *replayed || before.iter().any(|piece| after.contains(piece))
+            })
+        })
+    });
+
+    if !accounted {
+        return Err(RummikubError::RetrievedJokerNotReplayed);
+    }
+
+    Ok(played)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Color, Piece, RummikubError};
+    use crate::referee::check_turn;
+
+    #[test]
+    fn return_the_pieces_played() {
+        let run = vec!(Piece::normal(8, Color::Blue), Piece::normal(9, Color::Blue), Piece::normal(10, Color::Blue));
+        let rack = vec!(Piece::normal(11, Color::Blue), Piece::normal(7, Color::Blue), Piece::normal(2, Color::Red));
+
+        let mut longer = run.clone();
+        longer.push(Piece::normal(11, Color::Blue));
+        longer.insert(0, Piece::normal(7, Color::Blue));
+
+        let before = vec!(run.clone());
+        let after = vec!(longer.clone());
+
+        assert_eq!(check_turn(&before, &after, &rack, true),
+            Ok(vec!(Piece::normal(7, Color::Blue), Piece::normal(11, Color::Blue))));
+
+        assert_eq!(check_turn(&before, &before, &rack, true), Err(RummikubError::NothingPlayed));
+        assert_eq!(check_turn(&before, &after, &rack[1..], true),
+            Err(RummikubError::NotOnRack(Piece::normal(11, Color::Blue))));
+        assert_eq!(check_turn(&[run.clone(), longer], &after, &[], true),
+            Err(RummikubError::TakenFromBoard(Piece::normal(8, Color::Blue))));
+        assert_eq!(check_turn(&before, &[run, vec!(Piece::normal(2, Color::Red))], &rack, true),
+            Err(RummikubError::InvalidBoardSet(1)));
+        assert_eq!(check_turn(&before, &after, &rack, false),
+            Err(RummikubError::BoardChangedBeforeMeld));
+    }
+
+    #[test]
+    fn replay_retrieved_jokers() {
+        let before = vec!(vec!(Piece::normal(5, Color::Red), Piece::Joker, Piece::normal(7, Color::Red)));
+        let rack = vec!(
+            Piece::normal(6, Color::Red),
+            Piece::normal(9, Color::Black),
+            Piece::normal(9, Color::Orange),
+            Piece::normal(8, Color::Red),
+        );
+        let retrieved = vec!(Piece::normal(5, Color::Red), Piece::normal(6, Color::Red), Piece::normal(7, Color::Red));
+
+        // The joker stands for the red 6 until the red 6 takes its place
+        assert_eq!(check_turn(&before, &[
+            retrieved.clone(),
+            vec!(Piece::normal(9, Color::Black), Piece::Joker, Piece::normal(9, Color::Orange)),
+        ], &rack, true), Ok(vec!(
+            Piece::normal(6, Color::Red),
+            Piece::normal(9, Color::Black),
+            Piece::normal(9, Color::Orange),
+        )));
+
+        let mut kept = retrieved.clone();
+        kept.push(Piece::Joker);
+        assert_eq!(check_turn(&before, &[kept], &rack, true), Err(RummikubError::RetrievedJokerNotReplayed));
+
+        // Extending the run keeps the joker where it was
+        let mut extended = before[0].clone();
+        extended.push(Piece::normal(8, Color::Red));
+        assert_eq!(check_turn(&before, &[extended], &rack, true), Ok(vec!(Piece::normal(8, Color::Red))));
+    }
+
+    #[test]
+    fn replay_jokers_whatever_the_order_of_the_sets() {
+        let before = vec!(
+            vec!(Piece::normal(5, Color::Red), Piece::Joker, Piece::normal(7, Color::Red)),
+            vec!(Piece::normal(9, Color::Black), Piece::normal(9, Color::Blue), Piece::normal(9, Color::Orange)),
+        );
+        let rack = vec!(Piece::normal(6, Color::Red), Piece::normal(9, Color::Black), Piece::normal(9, Color::Red));
+
+        // Either black 9 could be the one from the rack, playing the joker again
+        // with the red 9
+        let mut after = vec!(
+            vec!(Piece::normal(5, Color::Red), Piece::normal(6, Color::Red), Piece::normal(7, Color::Red)),
+            before[1].clone(),
+            vec!(Piece::normal(9, Color::Black), Piece::normal(9, Color::Red), Piece::Joker),
+        );
+        assert!(check_turn(&before, &after, &rack, true).is_ok());
+
+        after.swap(1, 2);
+        assert!(check_turn(&before, &after, &rack, true).is_ok());
+    }
+
+    #[test]
+    fn count_jokers_as_the_set_is_ordered_for_the_meld() {
+        let rack = vec!(Piece::normal(9, Color::Red), Piece::normal(10, Color::Red), Piece::Joker);
+        let after = vec!(rack.clone());
+
+        // The joker goes before the run, as the red 8
+        assert_eq!(check_turn(&[], &after, &rack, false), Err(RummikubError::InitialMeldTooLow { points: 27, required: 30 }));
+
+        let rack = vec!(Piece::normal(11, Color::Red), Piece::normal(12, Color::Red), Piece::Joker);
+        let after = vec!(rack.clone());
+        assert_eq!(check_turn(&[], &after, &rack, false), Ok(rack));
+    }
+}
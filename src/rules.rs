@@ -0,0 +1,231 @@
+use std::ops::RangeInclusive;
+
+use serde::{Serialize, Deserialize};
+
+use crate::{Color, NormalPiece, Piece, RummikubError};
+use crate::piece::COLORS;
+
+// Runs going past the highest domination still have to count in a u8
+const MAX_DOMINATION: u8 = 99;
+
+static SPECIAL_JOKERS: [Piece; 3] = [Piece::MirrorJoker, Piece::ColorChangeJoker, Piece::DoubleJoker];
+
+/// Whether runs can go on from the highest domination to the lowest
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, Default, Serialize, Deserialize)]
+pub enum Wrap {
+    /// Runs stop at the highest domination
+    #[default]
+    None,
+    /// The lowest domination follows the highest, so 12-13-1 and 13-1-2 are both runs
+    Around,
+    /// The lowest domination can end a run after the highest, so 12-13-1 is a run
+    /// but 13-1-2 isn't
+    LowestAfterHighest,
+}
+
+/// The variable parts of the rules, the default is the standard 106 tile game
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
+pub struct RuleSet {
+    pub lowest_domination: u8,
+    pub highest_domination: u8,
+    /// The game uses this many of the colors, in the order of `Color::iterator`
+    pub colors: u8,
+    /// Copies of every normal piece
+    pub copies: u8,
+    pub jokers: u8,
+    pub min_set_length: u8,
+    #[serde(default)]
+    pub wrap: Wrap,
+    /// Whether the game has one of each special joker, see `Piece::is_special_joker`
+    #[serde(default)]
+    pub special_jokers: bool,
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        RuleSet {
+            lowest_domination: 1,
+            highest_domination: 13,
+            colors: 4,
+            copies: 2,
+            jokers: 2,
+            min_set_length: 3,
+            wrap: Wrap::None,
+            special_jokers: false,
+        }
+    }
+}
+
+impl RuleSet {
+    /// Checks the rules make sense, every piece needs a kind and a set needs
+    /// at least two pieces
+    pub fn check(&self) -> Result<(), RummikubError> {
+        if self.lowest_domination == 0 || self.lowest_domination > self.highest_domination {
+            return Err(RummikubError::InvalidRules("dominations have to be a range starting from at least 1"));
+        }
+
+        if self.highest_domination > MAX_DOMINATION {
+            return Err(RummikubError::InvalidRules("dominations can't go above 99"));
+        }
+
+        if self.colors == 0 || self.colors as usize > COLORS.len() {
+            return Err(RummikubError::InvalidRules("there have to be 1 to 4 colors"));
+        }
+
+        if self.min_set_length < 2 {
+            return Err(RummikubError::InvalidRules("sets have to be at least 2 pieces long"));
+        }
+
+        Ok(())
+    }
+
+    pub fn colors(&self) -> &'static [Color] {
+        &COLORS[..(self.colors as usize).min(COLORS.len())]
+    }
+
+    pub fn dominations(&self) -> RangeInclusive<u8> {
+        self.lowest_domination..=self.highest_domination
+    }
+
+    /// The domination after the given one in a run, if there is any
+    pub fn next_domination(&self, domination: u8) -> Option<u8> {
+        match self.wrap {
+            _ if domination < self.highest_domination => Some(domination + 1),
+            Wrap::None => None,
+            Wrap::Around | Wrap::LowestAfterHighest => Some(self.lowest_domination)
+        }
+    }
+
+    /// The longest a run can be, every domination once
+    pub fn max_run_length(&self) -> usize {
+        self.dominations().count()
+    }
+
+    /// The largest a group can be, every color once
+    pub fn max_group_length(&self) -> usize {
+        self.colors().len()
+    }
+
+    /// The most copies of a piece the game has
+    pub fn copies_of(&self, piece: &Piece) -> usize {
+        match piece {
+            Piece::Joker => self.jokers as usize,
+            Piece::Normal(_) => if self.check_piece(piece).is_ok() { self.copies as usize } else { 0 },
+            _ => if self.special_jokers { 1 } else { 0 }
+        }
+    }
+
+    pub fn check_piece(&self, piece: &Piece) -> Result<(), RummikubError> {
+        match piece {
+            Piece::Normal(normal) if !self.dominations().contains(&normal.domination) || !self.colors().contains(&normal.color) =>
+                Err(RummikubError::InvalidTile(*piece)),
+            _ if piece.is_special_joker() && !self.special_jokers => Err(RummikubError::InvalidTile(*piece)),
+            _ => Ok(())
+        }
+    }
+
+    // Every distinct piece gets an index, normal pieces are ordered by color
+    // and then by domination, so the pieces of a run are next to each other.
+    // The joker comes next, and then the special jokers when the game has them.
+    pub(crate) fn kinds(&self) -> usize {
+        self.joker_kind() + if self.special_jokers { 1 + SPECIAL_JOKERS.len() } else { 1 }
+    }
+
+    pub(crate) fn joker_kind(&self) -> usize {
+        self.colors().len() * self.max_run_length()
+    }
+
+    pub(crate) fn kind(&self, piece: &Piece) -> Option<usize> {
+        self.check_piece(piece).ok()?;
+
+        match piece {
+            Piece::Joker => Some(self.joker_kind()),
+            Piece::Normal(normal) => {
+                let color = self.colors().iter().position(|c| *c == normal.color)?;
+
+                Some(color * self.max_run_length() + (normal.domination - self.lowest_domination) as usize)
+            }
+            _ => Some(self.joker_kind() + 1 + SPECIAL_JOKERS.iter().position(|j| j == piece)?)
+        }
+    }
+
+    // How many pieces of every kind there are. The pieces have to be checked
+    // against the `TileBag` first, so they all have a kind.
+    pub(crate) fn kind_counts(&self, pieces: &[Piece]) -> Vec<u8> {
+        let mut counts = vec!(0; self.kinds());
+
+        for piece in pieces {
+            counts[self.kind(piece).expect("the pieces were checked")] += 1;
+        }
+
+        counts
+    }
+
+    // The pieces counted in `counts`, in the order they come in, taking them
+    // out of the counts
+    pub(crate) fn take_kinds(&self, pieces: &[Piece], counts: &mut [u8]) -> Vec<Piece> {
+        pieces.iter().filter(|piece| {
+            let k = self.kind(piece).expect("the pieces were checked");
+            let take = counts[k] > 0;
+            counts[k] -= take as u8;
+            take
+        }).copied().collect()
+    }
+
+    pub(crate) fn piece(&self, kind: usize) -> Piece {
+        if kind == self.joker_kind() {
+            Piece::Joker
+        } else if kind > self.joker_kind() {
+            SPECIAL_JOKERS[kind - self.joker_kind() - 1]
+        } else {
+            let color = self.colors()[kind / self.max_run_length()];
+            let domination = (kind % self.max_run_length()) as u8 + self.lowest_domination;
+
+            Piece::Normal(NormalPiece { domination, color })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Color, NormalPiece, Piece, RummikubError};
+    use crate::rules::RuleSet;
+
+    #[test]
+    fn map_pieces_to_kinds() {
+        let rules = RuleSet::default();
+
+        assert_eq!(rules.kind(&Piece::normal(1, Color::Black)), Some(0));
+        assert_eq!(rules.kind(&Piece::normal(13, Color::Orange)), Some(51));
+        assert_eq!(rules.kind(&Piece::Joker), Some(52));
+        assert_eq!(rules.kind(&Piece::Normal(NormalPiece { domination: 0, color: Color::Red })), None);
+
+        let rules = RuleSet { lowest_domination: 3, highest_domination: 9, colors: 3, ..RuleSet::default() };
+
+        assert_eq!(rules.kinds(), 22);
+        assert_eq!(rules.kind(&Piece::normal(1, Color::Black)), None);
+        assert_eq!(rules.kind(&Piece::normal(5, Color::Orange)), None);
+
+        for k in 0..rules.kinds() {
+            assert_eq!(rules.kind(&rules.piece(k)), Some(k));
+        }
+
+        assert_eq!(rules.kind(&Piece::DoubleJoker), None);
+
+        let rules = RuleSet { special_jokers: true, ..RuleSet::default() };
+        assert_eq!(rules.kinds(), 56);
+        assert_eq!(rules.kind(&Piece::DoubleJoker), Some(55));
+
+        for k in 0..rules.kinds() {
+            assert_eq!(rules.kind(&rules.piece(k)), Some(k));
+        }
+    }
+
+    #[test]
+    fn reject_rules_that_make_no_sense() {
+        assert_eq!(RuleSet::default().check(), Ok(()));
+        assert!(matches!(RuleSet { colors: 5, ..RuleSet::default() }.check(), Err(RummikubError::InvalidRules(_))));
+        assert!(matches!(RuleSet { lowest_domination: 0, ..RuleSet::default() }.check(), Err(RummikubError::InvalidRules(_))));
+        assert!(matches!(RuleSet { highest_domination: 250, ..RuleSet::default() }.check(), Err(RummikubError::InvalidRules(_))));
+    }
+}
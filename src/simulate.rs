@@ -0,0 +1,221 @@
+use std::io::{self, Write};
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use serde::Serialize;
+
+use crate::{Piece, RuleSet, RummikubError};
+use crate::bot::{bot, Strength};
+use crate::game::{Action, Game, GameState};
+
+/// What happened in a single game of a simulation
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct GameStats {
+    /// The index of the game in the simulation
+    pub game: usize,
+    /// The seed the racks were dealt and the bots played with
+    pub seed: u64,
+    /// Turns taken by every player together
+    pub turns: usize,
+    /// The round every player made their initial meld in, counting from 1, if they did
+    pub meld_rounds: Vec<Option<usize>>,
+    pub tiles_drawn: usize,
+    /// Jokers played from the racks to the board
+    pub jokers_played: usize,
+    /// Jokers still on the racks when the game ended
+    pub jokers_left: usize,
+    pub winner: usize,
+    /// Whether the game ended with the pool empty and nobody able to play
+    pub blocked: bool,
+    pub scores: Vec<i32>,
+}
+
+fn jokers(rack: &[Piece]) -> usize {
+    rack.iter().filter(|piece| !matches!(piece, Piece::Normal(_))).count()
+}
+
+pub fn simulate(players: &[Strength], games: usize, seed: u64) -> Result<Vec<GameStats>, RummikubError> {
+    simulate_with_rules(players, games, seed, &RuleSet::default())
+}
+
+/// Plays `games` games between bots of the strengths, in the order of their
+/// seats. Game `i` is dealt and played with the seed `seed + i`, so any game can
+/// be played again on its own.
+pub fn simulate_with_rules(players: &[Strength], games: usize, seed: u64, rules: &RuleSet) -> Result<Vec<GameStats>, RummikubError> {
+    (0..games).map(|game| {
+        let seed = seed.wrapping_add(game as u64);
+        let mut stats = play(players, seed, rules)?;
+        stats.game = game;
+        Ok(stats)
+    }).collect()
+}
+
+fn play(strengths: &[Strength], seed: u64, rules: &RuleSet) -> Result<GameStats, RummikubError> {
+    let mut game = Game::with_rules(strengths.len(), rules.clone(), &mut StdRng::seed_from_u64(seed))?;
+    let mut players: Vec<_> = strengths.iter().enumerate()
+        .map(|(seat, strength)| bot(*strength, seed.wrapping_add(seat as u64)))
+        .collect();
+
+    let mut turns = 0;
+    let mut meld_rounds = vec!(None; players.len());
+    let mut tiles_drawn = 0;
+    let mut jokers_played = 0;
+
+    while game.state() == GameState::Playing {
+        let current = game.current();
+        let jokers_before = jokers(&game.players()[current].rack);
+        let action = players[current].take_turn(&game.view());
+
+        match action {
+            Action::Draw => tiles_drawn += game.draw()?.is_some() as usize,
+            Action::Play(board) => {
+                game.play(board)?;
+                jokers_played += jokers_before - jokers(&game.players()[current].rack);
+                meld_rounds[current] = meld_rounds[current].or(Some(turns / players.len() + 1));
+            }
+        }
+
+        turns += 1;
+    }
+
+    let state = game.state();
+
+    Ok(GameStats {
+        game: 0,
+        seed,
+        turns,
+        meld_rounds,
+        tiles_drawn,
+        jokers_played,
+        jokers_left: game.players().iter().map(|seat| jokers(&seat.rack)).sum(),
+        winner: state.winner().unwrap(),
+        blocked: matches!(state, GameState::Blocked(_)),
+        scores: game.scores().unwrap(),
+    })
+}
+
+fn join<T: ToString>(values: &[T]) -> String {
+    values.iter().map(|value| value.to_string()).collect::<Vec<_>>().join(";")
+}
+
+/// Writes a row for every game, with a header. Values for every player are
+/// joined with `;`, in the order of their seats, and empty when there's none.
+pub fn write_csv<W: Write>(stats: &[GameStats], mut out: W) -> io::Result<()> {
+    writeln!(out, "game,seed,turns,meld_rounds,tiles_drawn,jokers_played,jokers_left,winner,blocked,scores")?;
+
+    for game in stats {
+        let meld_rounds: Vec<String> = game.meld_rounds.iter()
+            .map(|round| round.map(|round| round.to_string()).unwrap_or_default())
+            .collect();
+
+        writeln!(out, "{},{},{},{},{},{},{},{},{},{}",
+            game.game, game.seed, game.turns, meld_rounds.join(";"), game.tiles_drawn,
+            game.jokers_played, game.jokers_left, game.winner, game.blocked, join(&game.scores))?;
+    }
+
+    Ok(())
+}
+
+/// Writes the games as a JSON array
+pub fn write_json<W: Write>(stats: &[GameStats], out: W) -> io::Result<()> {
+    serde_json::to_writer_pretty(out, stats)?;
+    Ok(())
+}
+
+/// Averages over the games of a simulation
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct Summary {
+    pub games: usize,
+    pub mean_turns: f64,
+    pub mean_tiles_drawn: f64,
+    pub mean_jokers_played: f64,
+    /// How often every player won, in the order of their seats
+    pub win_rates: Vec<f64>,
+    /// How often every player made their initial meld on their first turn
+    pub first_round_melds: Vec<f64>,
+    pub blocked_rate: f64,
+}
+
+pub fn summarize(stats: &[GameStats]) -> Summary {
+    let games = stats.len();
+    let players = stats.first().map_or(0, |game| game.meld_rounds.len());
+    let mean = |value: &dyn Fn(&GameStats) -> usize| {
+        if games == 0 { 0.0 } else { stats.iter().map(value).sum::<usize>() as f64 / games as f64 }
+    };
+
+    Summary {
+        games,
+        mean_turns: mean(&|game| game.turns),
+        mean_tiles_drawn: mean(&|game| game.tiles_drawn),
+        mean_jokers_played: mean(&|game| game.jokers_played),
+        win_rates: (0..players).map(|seat| mean(&|game| (game.winner == seat) as usize)).collect(),
+        first_round_melds: (0..players).map(|seat| mean(&|game| (game.meld_rounds[seat] == Some(1)) as usize)).collect(),
+        blocked_rate: mean(&|game| game.blocked as usize),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::RuleSet;
+    use crate::bot::Strength;
+    use crate::simulate::{simulate_with_rules, summarize, write_csv, write_json};
+
+    // Fewer pieces keep the board small and the games quick
+    fn rules() -> RuleSet {
+        RuleSet { highest_domination: 9, copies: 1, ..RuleSet::default() }
+    }
+
+    #[test]
+    fn play_the_same_games_with_the_same_seed() {
+        let players = [Strength::Greedy, Strength::Conservative];
+        let stats = simulate_with_rules(&players, 2, 7, &rules()).unwrap();
+
+        assert_eq!(stats, simulate_with_rules(&players, 2, 7, &rules()).unwrap());
+
+        // Every game can be played again on its own
+        let mut again = simulate_with_rules(&players, 1, 8, &rules()).unwrap().remove(0);
+        again.game = 1;
+        assert_eq!(stats[1], again);
+
+        for game in &stats {
+            assert!(game.winner < 2);
+
+            let total = game.scores.iter().sum::<i32>();
+
+            if game.blocked {
+                // The winner's own rack counts against them once for every other player
+                assert!(total <= 0 && total % (players.len() as i32 - 1) == 0);
+                assert!((0..players.len()).all(|seat| seat == game.winner || game.scores[seat] <= 0));
+            } else {
+                assert_eq!(total, 0);
+            }
+
+            assert!(game.meld_rounds[game.winner].is_some());
+        }
+
+        let summary = summarize(&stats);
+        assert_eq!(summary.games, 2);
+        assert_eq!(summary.win_rates.iter().sum::<f64>(), 1.0);
+    }
+
+    #[test]
+    fn write_a_row_for_every_game() {
+        let stats = simulate_with_rules(&[Strength::Greedy, Strength::Endgame], 2, 1, &rules()).unwrap();
+
+        let mut csv = Vec::new();
+        write_csv(&stats, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("game,seed,turns,"));
+        assert!(lines[1].starts_with("0,1,"));
+        assert_eq!(lines[2].rsplit(',').next().unwrap().split(';').count(), 2);
+
+        let mut json = Vec::new();
+        write_json(&stats, &mut json).unwrap();
+        let games: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(games[1]["seed"], 2);
+        assert_eq!(games[1]["scores"].as_array().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,186 @@
+use std::rc::Rc;
+
+use crate::{Piece, RuleSet, RummikubError};
+use crate::deck::TileBag;
+use crate::solve_tile::{prepare, Search};
+use crate::solve_turn::TurnGoal;
+use crate::sort_set::sort_sets;
+
+// A set chosen for the anchor kind, with its position among the sets including that kind
+struct Chosen {
+    anchor: usize,
+    position: usize,
+    set: usize,
+    taken: Vec<u8>,
+}
+
+/// Iterates over every distinct way to arrange the pieces into valid sets.
+/// Two arrangements are the same if they are made of the same sets, in any order.
+pub struct Solutions {
+    search: Option<Search>,
+    state: Vec<u8>,
+    chosen: Vec<Chosen>,
+    resume: Option<usize>,
+    limit: Option<usize>,
+    found: usize,
+}
+
+impl Solutions {
+    /// Arrangements of the pieces that have to be placed in the state, there can't
+    /// be any optional ones. Without any pieces, the only arrangement has no sets.
+    pub(crate) fn from_state(search: Search, state: Vec<u8>, limit: Option<usize>) -> Self {
+        Solutions { search: Some(search), state, chosen: Vec::new(), resume: Some(0), limit, found: 0 }
+    }
+
+    // Moves on to the next complete arrangement, returns false once there are none left
+    fn advance(&mut self, search: &mut Search) -> bool {
+        let mut position = match self.resume.take() {
+            Some(position) => position,
+            None => match self.backtrack(search) {
+                None => return false,
+                Some(position) => position
+            }
+        };
+
+        loop {
+            let anchor = match (0..search.catalogue.rules.kinds()).find(|k| self.state[*k] > 0) {
+                None => return true,
+                Some(anchor) => anchor
+            };
+
+            // The same kind can be the anchor of a few sets in a row, choosing them
+            // in the order of the catalogue makes sure every arrangement shows up once
+            if let Some(last) = self.chosen.last() {
+                if last.anchor == anchor {
+                    position = position.max(last.position);
+                }
+            }
+
+            let catalogue = Rc::clone(&search.catalogue);
+            let mut next = None;
+
+            for (p, set) in catalogue.containing(anchor).iter().enumerate().skip(position) {
+                if !search.fits(&self.state, *set) {
+                    continue;
+                }
+
+                let taken = search.take(&mut self.state, *set);
+
+                // Skip sets that leave pieces which can't be arranged
+                if search.best(&mut self.state).is_some() {
+                    next = Some(Chosen { anchor, position: p, set: *set, taken });
+                    break;
+                }
+
+                search.put_back(&mut self.state, *set, &taken);
+            }
+
+            match next {
+                Some(chosen) => {
+                    self.chosen.push(chosen);
+                    position = 0;
+                }
+                None => match self.backtrack(search) {
+                    None => return false,
+                    Some(p) => position = p
+                }
+            }
+        }
+    }
+
+    // Undoes the last set chosen, returning the position to continue from
+    fn backtrack(&mut self, search: &Search) -> Option<usize> {
+        let last = self.chosen.pop()?;
+
+        search.put_back(&mut self.state, last.set, &last.taken);
+
+        Some(last.position + 1)
+    }
+}
+
+impl Iterator for Solutions {
+    type Item = Vec<Vec<Piece>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.limit.is_some_and(|limit| self.found >= limit) {
+            return None;
+        }
+
+        let mut search = self.search.take()?;
+
+        if !self.advance(&mut search) {
+            return None;
+        }
+
+        let sets = self.chosen.iter()
+            .map(|chosen| search.catalogue.sets[chosen.set].pieces.clone())
+            .collect();
+
+        // Sets of the catalogue are always valid, so they can always be ordered
+        let sets = sort_sets(sets, &search.catalogue.rules).expect("catalogue sets are valid");
+
+        self.search = Some(search);
+        self.found += 1;
+
+        Some(sets)
+    }
+}
+
+/// Every distinct arrangement of all the pieces into valid sets, up to `limit` of them
+pub fn solutions(pieces: Vec<Piece>, limit: Option<usize>) -> Result<Solutions, RummikubError> {
+    let rules = RuleSet::default();
+    TileBag::for_rules(&rules).check(&pieces)?;
+
+    let (state, catalogue) = prepare(&pieces, &[], &rules);
+
+    Ok(Solutions::from_state(Search::new(catalogue, TurnGoal::MostTiles), state, limit))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Color, Piece};
+    use crate::solutions::solutions;
+    use crate::solve_tile::solve_board;
+
+    #[test]
+    fn list_every_arrangement() {
+        // Either three runs, or three groups
+        let mut pieces = Vec::new();
+
+        for color in [Color::Black, Color::Blue, Color::Red] {
+            for domination in 1..4 {
+                pieces.push(Piece::normal(domination, color));
+            }
+        }
+
+        let all: Vec<Vec<Vec<Piece>>> = solutions(pieces.clone(), None).unwrap().collect();
+        assert_eq!(all.len(), 2);
+        assert!(all.contains(&solve_board(pieces.clone()).unwrap().unwrap()));
+        assert_ne!(all[0], all[1]);
+
+        assert_eq!(solutions(pieces, Some(1)).unwrap().count(), 1);
+
+        // No pieces are arranged in no sets
+        assert_eq!(solutions(vec!(), None).unwrap().collect::<Vec<_>>(), vec!(Vec::<Vec<Piece>>::new()));
+    }
+
+    #[test]
+    fn list_identical_sets_once() {
+        let run = vec!(
+            Piece::normal(1, Color::Red),
+            Piece::normal(2, Color::Red),
+            Piece::normal(3, Color::Red),
+        );
+        let mut pieces = run.clone();
+        pieces.extend(&run);
+
+        assert_eq!(solutions(pieces, None).unwrap().collect::<Vec<_>>(), vec!(
+            vec!(run.clone(), run)
+        ));
+    }
+
+    #[test]
+    fn list_nothing_when_unsolvable() {
+        assert_eq!(solutions(vec!(Piece::normal(1, Color::Red)), None).unwrap().count(), 0);
+    }
+}
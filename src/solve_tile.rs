@@ -1,117 +1,371 @@
-use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::rc::Rc;
 
 use itertools::Itertools;
 
-use crate::Piece;
+use crate::{Piece, RuleSet, RummikubError};
+use crate::catalogue::Catalogue;
+use crate::deck::TileBag;
+use crate::solve_turn::TurnGoal;
 use crate::sort_set::sort_sets;
-use crate::validate::valid_set;
+use crate::validate::valid_set_with_rules;
 
-fn tile_target(target: usize) -> Vec<[u8; 3]> {
-    let mut results = Vec::new();
+#[derive(Debug, Copy, Clone)]
+enum Step {
+    Done,
+    Skip(usize),
+    Set(usize),
+}
+
+/// The sets found to cover the pieces, and how many of the optional pieces
+/// of every kind were placed in them
+pub(crate) struct Cover {
+    pub(crate) sets: Vec<Vec<Piece>>,
+    pub(crate) placed: Vec<u8>,
+}
+
+// The state is the amount of every kind of piece left, first the pieces that
+// have to be placed, and then the optional ones
+pub(crate) struct Search {
+    pub(crate) catalogue: Rc<Catalogue>,
+    goal: TurnGoal,
+    kinds: usize,
+    bounds: Vec<u32>,
+    // The kinds by domination and then by color, see `best`
+    order: Vec<usize>,
+    scores: HashMap<usize, Vec<u32>>,
+    // The score of every kind of piece, when it doesn't depend on the set
+    values: Option<Vec<u32>>,
+    // Extra score for taking a set, whichever pieces it's taken from, and the
+    // entry of the state past the pieces counting how many more times it scores
+    bonuses: HashMap<usize, (u32, Option<usize>)>,
+    // How many times each set scores its bonus, in the order of the entries
+    rewarded_times: Vec<u8>,
+    memo: HashMap<Vec<u8>, Option<(u32, Step)>>,
+}
+
+impl Search {
+    pub(crate) fn new(catalogue: Rc<Catalogue>, goal: TurnGoal) -> Self {
+        let rules = &catalogue.rules;
+        let kinds = rules.kinds();
+        let bounds = (0..kinds).map(|k| goal.bound(&rules.piece(k), rules)).collect();
+        let order = (0..kinds).sorted_by_key(|k| match rules.piece(*k) {
+            Piece::Normal(normal) => (normal.domination, *k),
+            _ => (u8::MAX, *k)
+        }).collect();
+
+        Search {
+            catalogue,
+            goal,
+            kinds,
+            bounds,
+            order,
+            scores: HashMap::new(),
+            values: None,
+            bonuses: HashMap::new(),
+            rewarded_times: Vec::new(),
+            memo: HashMap::new(),
+        }
+    }
 
-    for c in 0..((target / 5) + 1) {
-        let left = target - (c * 5);
+    pub(crate) fn fits(&self, state: &[u8], set: usize) -> bool {
+        self.catalogue.sets[set].kinds.iter()
+            .all(|(k, count)| state[*k] + state[self.kinds + *k] >= *count)
+    }
+
+    // Takes the pieces of the set, the ones that have to be placed first, returning
+    // how many optional pieces were taken for every kind of the set. When the set
+    // scored its bonus, a last 1 follows.
+    pub(crate) fn take(&self, state: &mut [u8], set: usize) -> Vec<u8> {
+        let mut taken: Vec<u8> = self.catalogue.sets[set].kinds.iter().map(|(k, count)| {
+            let required = state[*k].min(*count);
+
+            state[*k] -= required;
+            state[self.kinds + *k] -= count - required;
 
-        for b in 0..((left / 4) + 1) {
-            let left2 = left - (b * 4);
+            count - required
+        }).collect();
 
-            if left2 % 3 == 0 {
-                results.push([
-                    (left2 / 3) as u8,
-                    b as u8,
-                    c as u8
-                ])
+        match self.bonuses.get(&set) {
+            Some((_, None)) => taken.push(1),
+            Some((_, Some(entry))) if state[*entry] > 0 => {
+                state[*entry] -= 1;
+                taken.push(1);
             }
+            _ => {}
         }
+
+        taken
     }
 
-    results
-}
+    pub(crate) fn put_back(&self, state: &mut [u8], set: usize, taken: &[u8]) {
+        let kinds = &self.catalogue.sets[set].kinds;
 
-fn find_valid(pieces: &Vec<Piece>, group_sizes: [u8; 3], left: &mut BTreeSet<usize>, current: &mut Vec<Vec<usize>>) -> bool {
-    let final_round = group_sizes[0] + group_sizes[1] + group_sizes[2] == 1;
+        for ((k, count), optional) in kinds.iter().zip(taken) {
+            state[*k] += count - optional;
+            state[self.kinds + *k] += optional;
+        }
 
-    let (next_group_size, group_size) = if group_sizes[0] > 0 {
-        ([group_sizes[0] - 1, group_sizes[1], group_sizes[2]], 3)
-    } else if group_sizes[1] > 0 {
-        ([group_sizes[0], group_sizes[1] - 1, group_sizes[2]], 4)
-    } else {
-        ([group_sizes[0], group_sizes[1], group_sizes[2] - 1], 5)
-    };
+        if taken.len() > kinds.len() {
+            if let Some(entry) = self.bonuses[&set].1 {
+                state[entry] += 1;
+            }
+        }
+    }
 
-    let snapshot = left.clone();
-    let mut resulting_set = Vec::new();
+    /// Scores every piece by its kind instead of by the goal. Has to be done
+    /// before searching.
+    pub(crate) fn value_kinds(&mut self, values: Vec<u32>) {
+        self.bounds = values.clone();
+        self.values = Some(values);
+    }
+
+    /// Scores taking the set, on top of the pieces it places, for the first `times`
+    /// it's taken or every time without a limit. Has to be done before searching,
+    /// with the states searched coming from `rewarded`.
+    pub(crate) fn reward(&mut self, set: usize, bonus: u32, times: Option<u8>) {
+        // Counting the times in the state keeps apart states that would be the same
+        let entry = times.map(|times| {
+            self.rewarded_times.push(times);
+            self.kinds * 2 + self.rewarded_times.len() - 1
+        });
 
-    for combination in snapshot.iter().combinations(group_size) {
-        resulting_set.clear();
+        self.bonuses.insert(set, (bonus, entry));
+    }
+
+    /// The state, with room to count the sets still rewarded
+    pub(crate) fn rewarded(&self, mut state: Vec<u8>) -> Vec<u8> {
+        state.extend(&self.rewarded_times);
+        state
+    }
 
-        for i in &combination {
-            resulting_set.push(pieces[**i].clone())
+    // Only the optional pieces placed count towards the score, and the bonus of the set
+    fn gained(&mut self, set: usize, taken: &[u8]) -> u32 {
+        let kinds = self.catalogue.sets[set].kinds.len();
+        let bonus = if taken.len() > kinds { self.bonuses[&set].0 } else { 0 };
+
+        if taken[..kinds].iter().all(|t| *t == 0) {
+            return bonus;
         }
 
-        if valid_set(&resulting_set) {
-            let mut indexes = Vec::new();
-            for i in &combination {
-                left.remove(*i);
-                indexes.push(**i);
+        let catalogue = Rc::clone(&self.catalogue);
+        let goal = self.goal;
+        let values = &self.values;
+        let scores = self.scores.entry(set).or_insert_with(|| match values {
+            None => goal.scores(&catalogue.sets[set].pieces, &catalogue.rules),
+            Some(values) => catalogue.sets[set].kinds.iter()
+                .flat_map(|(k, count)| std::iter::repeat_n(values[*k], *count as usize))
+                .collect()
+        });
+
+        let mut gained = 0;
+        let mut offset = 0;
+
+        // The pieces of the set are ordered by kind, the last ones of each kind
+        // are counted as the optional ones
+        for ((_, count), optional) in catalogue.sets[set].kinds.iter().zip(taken) {
+            let end = offset + *count as usize;
+            gained += scores[(end - *optional as usize)..end].iter().sum::<u32>();
+            offset = end;
+        }
+
+        gained + bonus
+    }
+
+    // The best score that can be gained from this state, or None if the pieces
+    // that have to be placed can't be
+    pub(crate) fn best(&mut self, state: &mut Vec<u8>) -> Option<u32> {
+        if let Some(known) = self.memo.get(state.as_slice()) {
+            return known.map(|(score, _)| score);
+        }
+
+        // The lowest kind left has to be either placed in some set or left out,
+        // so only the sets including it are attempted. Going by domination, the
+        // sets taken only use up pieces of the next few dominations, which keeps
+        // the states met few.
+        let anchor = match self.order.iter().copied().find(|k| state[*k] + state[self.kinds + *k] > 0) {
+            None => {
+                self.memo.insert(state.clone(), Some((0, Step::Done)));
+                return Some(0);
             }
+            Some(anchor) => anchor
+        };
 
-            current.push(indexes);
+        // Bonuses don't have a bound, the sets have to be weighed
+        let bound: u32 = if self.bonuses.is_empty() {
+            (0..self.kinds).map(|k| state[self.kinds + k] as u32 * self.bounds[k]).sum()
+        } else {
+            u32::MAX
+        };
+        let catalogue = Rc::clone(&self.catalogue);
+        let mut best: Option<(u32, Step)> = None;
 
-            if final_round {
-                return true;
-            } else {
-                if find_valid(pieces, next_group_size, left, current) {
-                    return true;
-                } else {
-                    // Revert the current push
-                    current.pop();
+        // Placing every piece scores the bound, unless a joker could be worth less
+        // than it, and finding out whether every piece can be placed is quicker
+        // than weighing which ones to leave out
+        let exact = self.values.is_some()
+            || matches!(self.goal, TurnGoal::MostTiles | TurnGoal::HighestValue)
+            || (self.catalogue.rules.joker_kind()..self.kinds).all(|k| state[self.kinds + k] == 0);
 
-                    // and the removal of the options from "left"
-                    for i in &combination {
-                        left.insert(**i);
-                    }
+        if exact && bound > 0 && self.bonuses.is_empty() {
+            let mut everything: Vec<u8> = (0..self.kinds).map(|k| state[k] + state[self.kinds + k]).collect();
+            everything.resize(self.kinds * 2, 0);
+
+            if self.best(&mut everything).is_some() {
+                if let Some((_, Step::Set(set))) = self.memo[&everything] {
+                    let taken = self.take(state, set);
+                    let gained = self.gained(set, &taken);
+                    let result = self.best(state);
+                    self.put_back(state, set, &taken);
+
+                    best = result.map(|score| (gained + score, Step::Set(set)));
                 }
             }
         }
-    }
-    false
-}
 
+        // Long runs are only placed as the shorter runs they split into, unless
+        // they score a bonus, and nothing can do better than placing everything
+        for set in catalogue.containing(anchor) {
+            if best.is_some_and(|(b, _)| b == bound) {
+                break;
+            }
 
-pub fn solve_board(pieces: Vec<Piece>) -> Option<Vec<Vec<Piece>>> {
-    let mut left = BTreeSet::new();
-    let mut current: Vec<Vec<usize>> = Vec::new();
+            let split = catalogue.sets[*set].split && !self.bonuses.contains_key(set);
+
+            if split || !self.fits(state, *set) {
+                continue;
+            }
 
-    for i in 0..pieces.len() {
-        left.insert(i);
+            let taken = self.take(state, *set);
+            let gained = self.gained(*set, &taken);
+            let result = self.best(state);
+            self.put_back(state, *set, &taken);
+
+            if let Some(score) = result {
+                if best.is_none_or(|(b, _)| gained + score > b) {
+                    best = Some((gained + score, Step::Set(*set)));
+                }
+            }
+        }
+
+        // An optional piece can also be left out
+        if state[anchor] == 0 && best.is_none_or(|(b, _)| b < bound) {
+            state[self.kinds + anchor] -= 1;
+            let result = self.best(state);
+            state[self.kinds + anchor] += 1;
+
+            if let Some(score) = result {
+                if best.is_none_or(|(b, _)| score > b) {
+                    best = Some((score, Step::Skip(anchor)));
+                }
+            }
+        }
+
+        self.memo.insert(state.clone(), best);
+        best.map(|(score, _)| score)
     }
 
-    for group_sizes in tile_target(pieces.len()) {
-        if find_valid(&pieces, group_sizes, &mut left, &mut current) {
-            let mut results = Vec::new();
+    pub(crate) fn cover(&mut self, mut state: Vec<u8>) -> Option<Cover> {
+        self.best(&mut state)?;
+
+        let mut sets = Vec::new();
+        let mut placed = vec!(0; self.kinds);
+
+        loop {
+            match self.memo[&state].unwrap().1 {
+                Step::Done => break,
+                Step::Skip(k) => state[self.kinds + k] -= 1,
+                Step::Set(set) => {
+                    let taken = self.take(&mut state, set);
 
-            for c in current {
-                let mut set = Vec::new();
+                    for ((k, _), optional) in self.catalogue.sets[set].kinds.iter().zip(taken) {
+                        placed[*k] += optional;
+                    }
 
-                for i in c {
-                    set.push(pieces[i].clone());
+                    sets.push(self.catalogue.sets[set].pieces.clone());
                 }
+            }
+        }
 
-                results.push(set);
+        Some(Cover { sets, placed })
+    }
+}
+
+// Long runs are placed as the shorter runs they split into, the ones that go on
+// from each other are joined back. Runs with jokers are left as they are, as
+// their jokers could end up worth less.
+pub(crate) fn join_runs(mut sets: Vec<Vec<Piece>>, rules: &RuleSet) -> Vec<Vec<Piece>> {
+    let plain = |set: &[Piece]| set.iter().all(|piece| matches!(piece, Piece::Normal(_)));
+
+    'joining: loop {
+        for (i, j) in (0..sets.len()).tuple_combinations() {
+            if !plain(&sets[i]) || !plain(&sets[j]) {
+                continue;
             }
 
-            return Some(sort_sets(results));
+            let mut joined = [sets[i].as_slice(), &sets[j]].concat();
+
+            if valid_set_with_rules(&joined, rules) {
+                joined.sort_by_key(|piece| rules.kind(piece));
+                sets[i] = joined;
+                sets.remove(j);
+                continue 'joining;
+            }
         }
+
+        return sets;
     }
+}
+
+/// The search state for the pieces, which have to be checked first
+pub(crate) fn state(required: &[Piece], optional: &[Piece], rules: &RuleSet) -> Vec<u8> {
+    [rules.kind_counts(required), rules.kind_counts(optional)].concat()
+}
+
+/// The search state for the pieces, and a catalogue with enough jokers for them
+pub(crate) fn prepare(required: &[Piece], optional: &[Piece], rules: &RuleSet) -> (Vec<u8>, Rc<Catalogue>) {
+    let state = state(required, optional, rules);
+    let joker = rules.joker_kind();
+    let kinds = rules.kinds();
+    let special_jokers = ((joker + 1)..kinds).any(|k| state[k] + state[kinds + k] > 0);
+    let catalogue = Catalogue::cached((state[joker] + state[kinds + joker]) as usize, special_jokers, rules);
+
+    (state, catalogue)
+}
+
+/// Places every required piece, and the optional pieces that score the most under
+/// the goal, into valid sets
+pub(crate) fn cover(required: &[Piece], optional: &[Piece], goal: TurnGoal, rules: &RuleSet) -> Option<Cover> {
+    let (state, catalogue) = prepare(required, optional, rules);
+    let found = Search::new(catalogue, goal).cover(state)?;
 
-    None
+    Some(Cover { sets: join_runs(found.sets, rules), placed: found.placed })
+}
+
+/// Arranges all the pieces into valid sets, or returns `None` if they can't be.
+/// No pieces make a board without any sets. Pieces that couldn't be a part of a
+/// game are rejected with an error.
+pub fn solve_board(pieces: Vec<Piece>) -> Result<Option<Vec<Vec<Piece>>>, RummikubError> {
+    solve_board_with_rules(pieces, &RuleSet::default())
+}
+
+/// Same as `solve_board`, for a game played by other rules
+pub fn solve_board_with_rules(pieces: Vec<Piece>, rules: &RuleSet) -> Result<Option<Vec<Vec<Piece>>>, RummikubError> {
+    rules.check()?;
+    TileBag::for_rules(rules).check(&pieces)?;
+
+    match cover(&pieces, &[], TurnGoal::MostTiles, rules) {
+        None => Ok(None),
+        Some(found) => Ok(Some(sort_sets(found.sets, rules)?))
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{Color, Piece};
-    use crate::solve_tile::{solve_board, tile_target};
+    use crate::{Color, NormalPiece, Piece, RuleSet, RummikubError, TileBag, Wrap};
+    use crate::solve_tile::{prepare, solve_board, solve_board_with_rules, Search};
+    use crate::solve_turn::{solve_turn, solve_turn_with_rules, TurnGoal};
+    use crate::validate::{valid_set, valid_set_with_rules};
 
     #[test]
     fn allow_case_with_2_groups() {
@@ -122,7 +376,7 @@ mod tests {
             Piece::normal(2, Color::Black),
             Piece::Joker,
             Piece::normal(2, Color::Blue)
-        )), Some(vec!(
+        )), Ok(Some(vec!(
             vec!(
                 Piece::normal(1, Color::Black),
                 Piece::normal(1, Color::Blue),
@@ -133,41 +387,192 @@ mod tests {
                 Piece::normal(2, Color::Blue),
                 Piece::Joker,
             )
+        ))));
+    }
+
+    #[test]
+    fn allow_runs_longer_than_five() {
+        let run: Vec<Piece> = (1..11).map(|d| Piece::normal(d, Color::Red)).collect();
+
+        assert_eq!(solve_board(run.clone()), Ok(Some(vec!(run.clone()))));
+
+        let mut pieces = run.clone();
+        pieces.push(Piece::normal(11, Color::Red));
+        pieces.push(Piece::normal(12, Color::Red));
+        pieces.push(Piece::normal(13, Color::Red));
+        pieces.push(Piece::normal(7, Color::Black));
+        pieces.push(Piece::normal(7, Color::Orange));
+
+        // The red 7 has to be taken out of the run to complete the group
+        let solution = solve_board(pieces).unwrap().unwrap();
+        assert!(solution.iter().all(valid_set));
+        assert!(solution.contains(&vec!(
+            Piece::normal(7, Color::Black),
+            Piece::normal(7, Color::Red),
+            Piece::normal(7, Color::Orange),
         )));
     }
 
     #[test]
-    fn test_1_option() {
-        assert_eq!(
-            tile_target(5),
-            vec!(
-                [0, 0, 1]
-            )
+    fn solve_full_board() {
+        let mut pieces = Vec::new();
+
+        for color in Color::iterator() {
+            for domination in 1..14 {
+                pieces.push(Piece::normal(domination, *color));
+                pieces.push(Piece::normal(domination, *color));
+            }
+        }
+
+        pieces.push(Piece::Joker);
+        pieces.push(Piece::Joker);
+
+        let solution = solve_board(pieces).unwrap().unwrap();
+        assert_eq!(solution.iter().map(|set| set.len()).sum::<usize>(), 106);
+        assert!(solution.iter().all(valid_set));
+    }
+
+    #[test]
+    fn reject_leftover_pieces() {
+        assert_eq!(solve_board(vec!(
+            Piece::normal(1, Color::Red),
+            Piece::normal(2, Color::Red),
+            Piece::normal(3, Color::Red),
+            Piece::normal(4, Color::Red),
+            Piece::normal(9, Color::Blue),
+        )), Ok(None));
+    }
+
+    #[test]
+    fn solve_an_empty_board() {
+        assert_eq!(solve_board(vec!()), Ok(Some(vec!())));
+        assert_eq!(solve_turn(vec!(), vec!()).unwrap().map(|turn| turn.sets), Some(vec!()));
+    }
+
+    #[test]
+    fn reject_impossible_pieces() {
+        let piece = Piece::Normal(NormalPiece { domination: 14, color: Color::Red });
+
+        assert_eq!(solve_board(vec!(
+            Piece::normal(12, Color::Red),
+            Piece::normal(13, Color::Red),
+            piece,
+        )), Err(RummikubError::InvalidTile(piece)));
+
+        assert_eq!(solve_board(vec!(
+            Piece::normal(5, Color::Red),
+            Piece::normal(5, Color::Red),
+            Piece::normal(5, Color::Red),
+        )), Err(RummikubError::TooManyCopies {
+            piece: Piece::normal(5, Color::Red),
+            count: 3,
+            max: 2,
+        }));
+    }
+
+    #[test]
+    fn solve_with_other_rules() {
+        let rules = RuleSet { colors: 3, min_set_length: 4, ..RuleSet::default() };
+        let run: Vec<Piece> = (1..4).map(|d| Piece::normal(d, Color::Red)).collect();
+
+        // Three pieces are too few for a set
+        assert_eq!(solve_board_with_rules(run.clone(), &rules), Ok(None));
+        assert_eq!(solve_board(run), Ok(Some(vec!((1..4).map(|d| Piece::normal(d, Color::Red)).collect()))));
+
+        let pieces = vec!(
+            Piece::normal(1, Color::Red),
+            Piece::normal(2, Color::Red),
+            Piece::normal(3, Color::Red),
+            Piece::Joker,
         );
+        let solution = solve_board_with_rules(pieces, &rules).unwrap().unwrap();
+        assert!(solution.iter().all(|set| valid_set_with_rules(set, &rules)));
 
-        assert_eq!(
-            tile_target(6),
-            vec!(
-                [2, 0, 0]
-            )
+        // Orange isn't one of the three colors
+        let orange = Piece::normal(5, Color::Orange);
+        assert_eq!(solve_board_with_rules(vec!(orange), &rules), Err(RummikubError::InvalidTile(orange)));
+    }
+
+    #[test]
+    fn solve_with_wrapped_runs() {
+        let rules = RuleSet { wrap: Wrap::Around, ..RuleSet::default() };
+        let pieces = vec!(
+            Piece::normal(13, Color::Black),
+            Piece::normal(1, Color::Black),
+            Piece::normal(2, Color::Black),
+            Piece::normal(12, Color::Black),
         );
 
-        assert_eq!(
-            tile_target(20),
-            vec!(
-                [4, 2, 0],
-                [0, 5, 0],
-                [5, 0, 1],
-                [1, 3, 1],
-                [2, 1, 2],
-                [0, 0, 4]
-            )
+        assert_eq!(solve_board(pieces.clone()), Ok(None));
+        assert_eq!(solve_board_with_rules(pieces, &rules), Ok(Some(vec!(vec!(
+            Piece::normal(12, Color::Black),
+            Piece::normal(13, Color::Black),
+            Piece::normal(1, Color::Black),
+            Piece::normal(2, Color::Black),
+        )))));
+    }
+
+    #[test]
+    fn solve_with_special_jokers() {
+        let rules = RuleSet { special_jokers: true, ..RuleSet::default() };
+        let pieces = vec!(
+            Piece::normal(8, Color::Orange),
+            Piece::DoubleJoker,
+            Piece::normal(11, Color::Orange),
+            Piece::normal(2, Color::Blue),
+            Piece::ColorChangeJoker,
+            Piece::normal(4, Color::Red),
         );
+
+        assert!(solve_board(pieces.clone()).is_err());
+        assert_eq!(solve_board_with_rules(pieces, &rules), Ok(Some(vec!(
+            vec!(Piece::normal(2, Color::Blue), Piece::ColorChangeJoker, Piece::normal(4, Color::Red)),
+            vec!(Piece::normal(8, Color::Orange), Piece::DoubleJoker, Piece::normal(11, Color::Orange)),
+        ))));
+
+        // The solver places a special joker with another joker only when the set is valid
+        for special in [Piece::MirrorJoker, Piece::DoubleJoker, Piece::ColorChangeJoker] {
+            let set = vec!(Piece::normal(5, Color::Red), Piece::Joker, special);
+            let solved = solve_board_with_rules(set.clone(), &rules).unwrap();
+
+            assert_eq!(solved.is_some(), valid_set_with_rules(&set, &rules));
+        }
+
+        // The double joker can't take the joker along with the red 1
+        let rack = vec!(Piece::normal(1, Color::Red), Piece::Joker);
+        let turn = solve_turn_with_rules(vec!(vec!(Piece::DoubleJoker)), rack, TurnGoal::MostTiles, &rules).unwrap().unwrap();
+        assert_eq!(turn.played, vec!(Piece::normal(1, Color::Red)));
+        assert!(turn.sets.iter().all(|set| valid_set_with_rules(set, &rules)));
     }
 
     #[test]
-    fn tile_full_board() {
-        let options = tile_target(98);
-        assert_eq!(options.len(), 90);
+    fn reward_a_set_only_as_many_times_as_asked() {
+        let rules = RuleSet::default();
+        let run = vec!(Piece::normal(1, Color::Red), Piece::normal(2, Color::Red), Piece::normal(3, Color::Red));
+        let (state, catalogue) = prepare(&[run.clone(), run.clone()].concat(), &[], &rules);
+        let set = catalogue.sets.iter().position(|set| set.pieces == run).unwrap();
+
+        for times in [1, 2] {
+            let mut search = Search::new(catalogue.clone(), TurnGoal::MostTiles);
+            search.reward(set, 1, Some(times));
+
+            assert_eq!(search.best(&mut search.rewarded(state.clone())), Some(times as u32));
+        }
+    }
+
+    #[test]
+    fn solve_partial_boards_quickly() {
+        let bag = TileBag::standard().pieces();
+        let some = |every: usize, kept: usize| -> Vec<Piece> {
+            bag.iter().enumerate().filter(|(i, _)| i % every < kept).map(|(_, piece)| *piece).collect()
+        };
+
+        // Placing as many as possible of these 64 pieces used to take half a minute
+        assert_eq!(solve_board(some(5, 3)), Ok(None));
+        assert_eq!(solve_turn(vec!(), some(5, 3)).unwrap().unwrap().played.len(), 51);
+
+        let pieces = some(4, 3);
+        assert!(solve_board(pieces.clone()).unwrap().unwrap().iter().all(valid_set));
+        assert_eq!(solve_turn(vec!(), pieces).unwrap().unwrap().played.len(), 80);
     }
-}
\ No newline at end of file
+}
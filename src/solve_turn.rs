@@ -0,0 +1,156 @@
+use crate::{Piece, RuleSet, RummikubError};
+use crate::deck::TileBag;
+use crate::solve_tile::cover;
+use crate::sort_set::{effective_dominations, sort_sets};
+
+/// What a turn should maximize when more than one rearrangement is possible
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum TurnGoal {
+    MostTiles,
+    HighestValue,
+    /// Meld points, where a joker is worth the domination it stands for
+    MeldPoints,
+}
+
+impl TurnGoal {
+    // The score of every piece of a valid set, in the order given
+    pub(crate) fn scores(&self, set: &[Piece], rules: &RuleSet) -> Vec<u32> {
+        match self {
+            TurnGoal::MostTiles => vec!(1; set.len()),
+            TurnGoal::HighestValue => set.iter().map(|p| p.value() as u32).collect(),
+            TurnGoal::MeldPoints => effective_dominations(set, rules).iter().map(|d| *d as u32).collect()
+        }
+    }
+
+    // The most a piece could ever score, whichever set it ends up in
+    pub(crate) fn bound(&self, piece: &Piece, rules: &RuleSet) -> u32 {
+        match (self, piece) {
+            (TurnGoal::MostTiles, _) => 1,
+            (TurnGoal::HighestValue, _) => piece.value() as u32,
+            (TurnGoal::MeldPoints, Piece::Normal(normal)) => normal.domination as u32,
+            (TurnGoal::MeldPoints, Piece::MirrorJoker) => 0,
+            (TurnGoal::MeldPoints, _) => {
+                let highest = rules.highest_domination as u32;
+
+                if *piece == Piece::DoubleJoker { highest * 2 } else { highest }
+            }
+        }
+    }
+}
+
+/// A rearrangement of the board, and the rack pieces it places
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Turn {
+    pub sets: Vec<Vec<Piece>>,
+    pub played: Vec<Piece>,
+}
+
+/// Finds the rearrangement of the board that places the most rack pieces,
+/// while still using every piece already on the board.
+pub fn solve_turn(board: Vec<Vec<Piece>>, rack: Vec<Piece>) -> Result<Option<Turn>, RummikubError> {
+    solve_turn_for(board, rack, TurnGoal::MostTiles)
+}
+
+/// Same as `solve_turn`, but with a choice of what the placed pieces should maximize.
+/// Returns `None` when the board pieces can't be arranged at all.
+pub fn solve_turn_for(board: Vec<Vec<Piece>>, rack: Vec<Piece>, goal: TurnGoal) -> Result<Option<Turn>, RummikubError> {
+    solve_turn_with_rules(board, rack, goal, &RuleSet::default())
+}
+
+/// Same as `solve_turn_for`, for a game played by other rules
+pub fn solve_turn_with_rules(
+    board: Vec<Vec<Piece>>,
+    rack: Vec<Piece>,
+    goal: TurnGoal,
+    rules: &RuleSet,
+) -> Result<Option<Turn>, RummikubError> {
+    rules.check()?;
+
+    let board: Vec<Piece> = board.into_iter().flatten().collect();
+    let mut all = board.clone();
+    all.extend(&rack);
+    TileBag::for_rules(rules).check(&all)?;
+
+    let mut found = match cover(&board, &rack, goal, rules) {
+        None => return Ok(None),
+        Some(found) => found
+    };
+    // List the pieces placed in the order they were on the rack
+    let played = rules.take_kinds(&rack, &mut found.placed);
+
+    Ok(Some(Turn {
+        sets: sort_sets(found.sets, rules)?,
+        played,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Color, Piece};
+    use crate::solve_turn::{solve_turn, solve_turn_for, Turn, TurnGoal};
+
+    #[test]
+    fn extend_board_with_rack() {
+        assert_eq!(solve_turn(
+            vec!(vec!(
+                Piece::normal(1, Color::Red),
+                Piece::normal(2, Color::Red),
+                Piece::normal(3, Color::Red),
+            )),
+            vec!(
+                Piece::normal(4, Color::Red),
+                Piece::normal(9, Color::Blue),
+                Piece::Joker,
+            ),
+        ), Ok(Some(Turn {
+            sets: vec!(vec!(
+                Piece::normal(1, Color::Red),
+                Piece::normal(2, Color::Red),
+                Piece::normal(3, Color::Red),
+                Piece::normal(4, Color::Red),
+                Piece::Joker,
+            )),
+            played: vec!(
+                Piece::normal(4, Color::Red),
+                Piece::Joker,
+            ),
+        })));
+    }
+
+    #[test]
+    fn keep_rack_when_nothing_fits() {
+        let board = vec!(vec!(
+            Piece::normal(5, Color::Black),
+            Piece::normal(5, Color::Blue),
+            Piece::normal(5, Color::Orange),
+        ));
+
+        assert_eq!(solve_turn(board.clone(), vec!(Piece::normal(9, Color::Red))), Ok(Some(Turn {
+            sets: board,
+            played: vec!(),
+        })));
+
+        // The board pieces can't be left out
+        assert_eq!(solve_turn(
+            vec!(vec!(Piece::normal(5, Color::Black), Piece::normal(7, Color::Black))),
+            vec!(Piece::normal(9, Color::Red)),
+        ), Ok(None));
+    }
+
+    #[test]
+    fn prefer_highest_value() {
+        let rack = vec!(
+            Piece::Joker,
+            Piece::normal(1, Color::Red),
+            Piece::normal(2, Color::Red),
+            Piece::normal(12, Color::Blue),
+            Piece::normal(13, Color::Blue),
+        );
+
+        assert_eq!(solve_turn_for(vec!(), rack, TurnGoal::HighestValue).unwrap().unwrap().played, vec!(
+            Piece::Joker,
+            Piece::normal(12, Color::Blue),
+            Piece::normal(13, Color::Blue),
+        ));
+    }
+}
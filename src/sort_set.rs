@@ -1,8 +1,21 @@
 use std::cmp::Ordering;
 use std::collections::BTreeSet;
 
-use crate::{Color, Piece};
-use crate::common::{ConsecutiveSet, first_non_joker, same_domination};
+use itertools::Itertools;
+
+use crate::{Color, NormalPiece, Piece, RuleSet, RummikubError};
+use crate::common::{ConsecutiveSet, first_non_joker};
+use crate::validate::{all_stand_ins, is_group, mirrored_half, stand_ins, valid_set_with_rules};
+
+/// What a piece of a set stands for
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct EffectiveTile {
+    pub piece: Piece,
+    /// Every choice of what the piece stands for, the one used to order the set first.
+    /// A normal piece stands for itself, a double joker for two pieces and a mirror
+    /// joker for none.
+    pub options: Vec<Vec<NormalPiece>>,
+}
 
 struct SameDominationPiece {
     piece: Piece,
@@ -21,16 +34,219 @@ struct SetOrder {
     set: Vec<Piece>,
 }
 
+// Pairs every piece of a run with the domination it stands for, in the order given.
+// Jokers take the first domination of the run still available.
+fn same_color_pieces(input: &Vec<Piece>, set: &ConsecutiveSet) -> Result<Vec<SameColorPiece>, RummikubError> {
+    let mut available_domination = set.available_domination();
+
+    for piece in input {
+        if let Piece::Normal(normal) = piece {
+            available_domination.remove(&normal.domination);
+        }
+    }
+
+    let mut color_pieces = Vec::new();
+
+    for piece in input {
+        match piece {
+            Piece::Normal(n) => {
+                color_pieces.push(SameColorPiece {
+                    piece: Piece::Normal(*n),
+                    effective_domination: n.domination,
+                })
+            }
+            joker => {
+                let first = *available_domination.iter().min_by_key(|d| set.offset(**d)).ok_or(RummikubError::InvalidSet)?;
+
+                color_pieces.push(SameColorPiece {
+                    piece: *joker,
+                    effective_domination: available_domination.take(&first).unwrap(),
+                });
+            }
+        }
+    }
+
+    Ok(color_pieces)
+}
+
+// Orders a set with a special joker, along with the pieces every piece of the order
+// stands for. The special joker goes where the first piece it stands for would.
+fn special_order(input: &[Piece], rules: &RuleSet) -> Result<(SetOrder, Vec<Vec<NormalPiece>>), RummikubError> {
+    if input.contains(&Piece::MirrorJoker) {
+        let (half, half_pieces) = SetOrder::resolve(mirrored_half(input).ok_or(RummikubError::InvalidSet)?, rules)?;
+
+        let mut set = half.set.clone();
+        set.push(Piece::MirrorJoker);
+        set.extend(half.set.iter().rev());
+
+        let mut pieces = half_pieces.clone();
+        pieces.push(vec!());
+        pieces.extend(half_pieces.into_iter().rev());
+
+        return Ok((SetOrder { set, ..half }, pieces));
+    }
+
+    let special = *input.iter().find(|piece| piece.is_special_joker()).ok_or(RummikubError::InvalidSet)?;
+    let stand_ins = stand_ins(input, rules).ok_or(RummikubError::InvalidSet)?;
+
+    let mut expanded: Vec<Piece> = input.iter().filter(|piece| !piece.is_special_joker()).copied().collect();
+    expanded.extend(stand_ins.iter().map(|normal| Piece::Normal(*normal)));
+
+    let (order, expanded_pieces) = SetOrder::resolve(expanded, rules)?;
+
+    let mut set = Vec::new();
+    let mut pieces: Vec<Vec<NormalPiece>> = Vec::new();
+    let mut special_at = None;
+
+    for (piece, stands_for) in order.set.iter().zip(expanded_pieces) {
+        match (piece, special_at) {
+            (Piece::Normal(normal), None) if stand_ins.contains(normal) => {
+                special_at = Some(set.len());
+                set.push(special);
+                pieces.push(stands_for);
+            }
+            (Piece::Normal(normal), Some(at)) if stand_ins.contains(normal) => pieces[at].extend(stands_for),
+            _ => {
+                set.push(*piece);
+                pieces.push(stands_for);
+            }
+        }
+    }
+
+    Ok((SetOrder { set, ..order }, pieces))
+}
+
+// The pieces every piece of a valid set stands for, in the order given
+fn effective_pieces(input: &[Piece], rules: &RuleSet) -> Result<Vec<Vec<NormalPiece>>, RummikubError> {
+    let (order, pieces) = SetOrder::resolve(input.to_vec(), rules)?;
+    let mut left: Vec<(Piece, Vec<NormalPiece>)> = order.set.into_iter().zip(pieces).collect();
+
+    // Back to the order given, jokers of a kind can be swapped with each other
+    Ok(input.iter().map(|piece| {
+        let position = left.iter().position(|(p, _)| p == piece).unwrap();
+        left.remove(position).1
+    }).collect())
+}
+
+/// The domination every piece of a valid set stands for, in the order given.
+/// Jokers in a run are placed the same way the set is ordered for display.
+/// A double joker stands for the sum of its two pieces, and a mirror joker for nothing.
+pub(crate) fn effective_dominations(input: &[Piece], rules: &RuleSet) -> Vec<u8> {
+    match effective_pieces(input, rules) {
+        // Jokers that don't stand for anything are worth nothing
+        Err(_) => vec!(0; input.len()),
+        Ok(pieces) => pieces.iter()
+            .map(|stands_for| stands_for.iter().map(|normal| normal.domination).sum())
+            .collect()
+    }
+}
+
+/// What every piece of a valid set stands for, in the order given
+pub fn resolve_jokers(set: &[Piece]) -> Result<Vec<EffectiveTile>, RummikubError> {
+    resolve_jokers_with_rules(set, &RuleSet::default())
+}
+
+pub fn resolve_jokers_with_rules(set: &[Piece], rules: &RuleSet) -> Result<Vec<EffectiveTile>, RummikubError> {
+    if !set.is_empty() && set.iter().all(|piece| *piece == Piece::Joker) {
+        return Err(RummikubError::JokerOnlySet);
+    }
+
+    if !valid_set_with_rules(&set.to_vec(), rules) {
+        return Err(RummikubError::InvalidSet);
+    }
+
+    let pieces = effective_pieces(set, rules)?;
+
+    let jokers: Vec<usize> = (0..set.len()).filter(|i| set[*i] == Piece::Joker).collect();
+
+    // Every piece a joker could be swapped for on its own, keeping the set valid
+    let alone = |joker: usize| -> Vec<NormalPiece> {
+        (0..rules.joker_kind()).filter_map(|k| match rules.piece(k) {
+            Piece::Normal(normal) => {
+                let mut swapped = set.to_vec();
+                swapped[joker] = Piece::Normal(normal);
+
+                Some(normal).filter(|_| valid_set_with_rules(&swapped, rules))
+            }
+            _ => None
+        }).collect()
+    };
+
+    // A joker only stands for the pieces it takes when every joker is swapped
+    // and the set stays valid
+    let mut candidates: Vec<Vec<NormalPiece>> = vec!(Vec::new(); jokers.len());
+    let mut assignments: Vec<Vec<usize>> = Vec::new();
+
+    if !jokers.is_empty() {
+        for assignment in jokers.iter().map(|joker| alone(*joker)).multi_cartesian_product() {
+            let mut swapped = set.to_vec();
+
+            for (joker, normal) in jokers.iter().zip(&assignment) {
+                swapped[*joker] = Piece::Normal(*normal);
+            }
+
+            if !valid_set_with_rules(&swapped, rules) {
+                continue;
+            }
+
+            for (options, normal) in candidates.iter_mut().zip(&assignment) {
+                if !options.contains(normal) {
+                    options.push(*normal);
+                }
+            }
+
+            // The jokers are interchangeable, so only the pieces they stand for matter
+            let kinds = assignment.iter().filter_map(|normal| rules.kind(&Piece::Normal(*normal))).sorted().collect();
+
+            if !assignments.contains(&kinds) {
+                assignments.push(kinds);
+            }
+        }
+    }
+
+    // With a single way to swap the jokers there's no choice to make
+    let ambiguous = assignments.len() > 1;
+    let mut candidates = candidates.into_iter();
+
+    Ok(set.iter().zip(pieces).map(|(piece, stands_for)| {
+        let others: Vec<Vec<NormalPiece>> = match piece {
+            Piece::Joker => {
+                let options = candidates.next().unwrap_or_default();
+
+                if ambiguous { options.into_iter().map(|normal| vec!(normal)).collect() } else { vec!() }
+            }
+            Piece::DoubleJoker | Piece::ColorChangeJoker => all_stand_ins(set, rules),
+            _ => vec!()
+        };
+
+        let mut options = vec!(stands_for);
+
+        for option in others {
+            if !options.contains(&option) {
+                options.push(option);
+            }
+        }
+
+        EffectiveTile { piece: *piece, options }
+    }).collect())
+}
+
 impl SetOrder {
-    fn new(input: Vec<Piece>) -> SetOrder {
-        let first_piece = first_non_joker(&input).unwrap();
+    fn new(input: Vec<Piece>, rules: &RuleSet) -> Result<SetOrder, RummikubError> {
+        Ok(SetOrder::resolve(input, rules)?.0)
+    }
+
+    // Orders the set, along with the pieces every piece of the order stands for
+    fn resolve(input: Vec<Piece>, rules: &RuleSet) -> Result<(SetOrder, Vec<Vec<NormalPiece>>), RummikubError> {
+        if input.iter().any(Piece::is_special_joker) {
+            return special_order(&input, rules);
+        }
 
-        if same_domination(first_piece, &input) {
-            let mut available_colors = BTreeSet::new();
-            available_colors.insert(Color::Black);
-            available_colors.insert(Color::Blue);
-            available_colors.insert(Color::Red);
-            available_colors.insert(Color::Orange);
+        let first_piece = first_non_joker(&input).ok_or(RummikubError::JokerOnlySet)?;
+
+        // Jokers that don't fit in the colors left make a run of the piece instead
+        if is_group(first_piece, &input, rules) {
+            let mut available_colors: BTreeSet<Color> = rules.colors().iter().copied().collect();
 
             for piece in &input {
                 if let Piece::Normal(n) = piece {
@@ -42,20 +258,20 @@ impl SetOrder {
 
             for piece in &input {
                 match piece {
-                    Piece::Joker => {
-                        let first = available_colors.iter().next().unwrap().to_owned();
-
-                        domination_pieces.push(SameDominationPiece {
-                            piece: Piece::Joker,
-                            effective_color: available_colors.take(&first).unwrap(),
-                        });
-                    }
                     Piece::Normal(n) => {
                         domination_pieces.push(SameDominationPiece {
-                            piece: Piece::Normal(n.clone()),
+                            piece: Piece::Normal(*n),
                             effective_color: n.color,
                         })
                     }
+                    joker => {
+                        let first = available_colors.iter().next().ok_or(RummikubError::InvalidSet)?.to_owned();
+
+                        domination_pieces.push(SameDominationPiece {
+                            piece: *joker,
+                            effective_color: available_colors.take(&first).unwrap(),
+                        });
+                    }
                 }
             }
 
@@ -65,53 +281,31 @@ impl SetOrder {
 
             let first_color = domination_pieces[0].effective_color;
 
-            SetOrder {
+            Ok((SetOrder {
                 effective_color: first_color,
                 effective_domination: first_piece.domination,
                 set: domination_pieces.iter().map(|dp| dp.piece).collect(),
-            }
+            }, domination_pieces.iter().map(|dp| vec!(NormalPiece {
+                domination: first_piece.domination,
+                color: dp.effective_color,
+            })).collect()))
         } else {
-            let set = ConsecutiveSet::new(&input)
-                .unwrap();
-
-            let mut available_domination = set.available_domination();
+            let set = ConsecutiveSet::new(&input, rules)
+                .filter(|set| set.is_valid())
+                .ok_or(RummikubError::InvalidSet)?;
 
-            for piece in &input {
-                if let Piece::Normal(normal) = piece {
-                    available_domination.remove(&normal.domination);
-                }
-            }
+            let mut color_pieces = same_color_pieces(&input, &set)?;
 
-            let mut color_pieces = Vec::new();
+            color_pieces.sort_by_key(|cp| set.offset(cp.effective_domination));
 
-            for piece in &input {
-                match piece {
-                    Piece::Joker => {
-                        let first = *available_domination.iter().next().unwrap();
-
-                        color_pieces.push(SameColorPiece {
-                            piece: Piece::Joker,
-                            effective_domination: available_domination.take(&first).unwrap(),
-                        });
-                    }
-                    Piece::Normal(n) => {
-                        color_pieces.push(SameColorPiece {
-                            piece: Piece::Normal(n.clone()),
-                            effective_domination: n.domination,
-                        })
-                    }
-                }
-            }
-
-            color_pieces.sort_by(|cp1, cp2| {
-                cp1.effective_domination.partial_cmp(&cp2.effective_domination).unwrap()
-            });
-
-            SetOrder {
+            Ok((SetOrder {
                 effective_color: first_piece.color,
                 effective_domination: set.actual_first(),
                 set: color_pieces.iter().map(|dp| dp.piece).collect(),
-            }
+            }, color_pieces.iter().map(|cp| vec!(match cp.piece {
+                Piece::Normal(normal) => normal,
+                _ => NormalPiece { domination: cp.effective_domination, color: first_piece.color }
+            })).collect()))
         }
     }
 }
@@ -132,25 +326,99 @@ impl Ord for SetOrder {
     }
 }
 
-pub(crate) fn sort_sets(sets: Vec<Vec<Piece>>) -> Vec<Vec<Piece>> {
+pub(crate) fn sort_sets(sets: Vec<Vec<Piece>>, rules: &RuleSet) -> Result<Vec<Vec<Piece>>, RummikubError> {
     let mut sorted = Vec::new();
 
     for set in sets {
-        sorted.push(SetOrder::new(set));
+        sorted.push(SetOrder::new(set, rules)?);
     }
 
     sorted.sort();
 
     // figure out if possible not to clone
-    sorted.iter().map(|s| s.set.clone()).collect()
+    Ok(sorted.iter().map(|s| s.set.clone()).collect())
 }
 
 
 #[cfg(test)]
 mod tests {
-    use crate::Piece;
+    use crate::{NormalPiece, Piece, RuleSet, RummikubError, Wrap};
     use crate::piece::Color;
-    use crate::sort_set::{SetOrder, sort_sets};
+    use crate::solve_tile::solve_board_with_rules;
+    use crate::sort_set::{effective_dominations, EffectiveTile, resolve_jokers, resolve_jokers_with_rules, SetOrder, sort_sets};
+
+    #[test]
+    fn resolve_effective_dominations() {
+        assert_eq!(effective_dominations(&[
+            Piece::normal(7, Color::Red),
+            Piece::Joker,
+            Piece::normal(7, Color::Blue),
+        ], &RuleSet::default()), vec!(7, 7, 7));
+
+        assert_eq!(effective_dominations(&[
+            Piece::normal(13, Color::Red),
+            Piece::Joker,
+            Piece::normal(11, Color::Red),
+            Piece::Joker,
+        ], &RuleSet::default()), vec!(13, 10, 11, 12));
+    }
+
+    #[test]
+    fn resolve_what_jokers_stand_for() {
+        let normal = |domination, color| NormalPiece { domination, color };
+
+        // The only way to fill the run
+        assert_eq!(resolve_jokers(&[
+            Piece::normal(4, Color::Red),
+            Piece::Joker,
+            Piece::Joker,
+            Piece::normal(1, Color::Red),
+        ]), Ok(vec!(
+            EffectiveTile { piece: Piece::normal(4, Color::Red), options: vec!(vec!(normal(4, Color::Red))) },
+            EffectiveTile { piece: Piece::Joker, options: vec!(vec!(normal(2, Color::Red))) },
+            EffectiveTile { piece: Piece::Joker, options: vec!(vec!(normal(3, Color::Red))) },
+            EffectiveTile { piece: Piece::normal(1, Color::Red), options: vec!(vec!(normal(1, Color::Red))) },
+        )));
+
+        // Either of the missing colors
+        let group = resolve_jokers(&[Piece::normal(9, Color::Blue), Piece::Joker, Piece::normal(9, Color::Orange)]).unwrap();
+        assert_eq!(group[1].options, vec!(vec!(normal(9, Color::Black)), vec!(normal(9, Color::Red))));
+
+        // Before or after the run
+        let run = resolve_jokers(&[Piece::normal(6, Color::Black), Piece::normal(7, Color::Black), Piece::Joker]).unwrap();
+        assert_eq!(run[2].options, vec!(vec!(normal(5, Color::Black)), vec!(normal(8, Color::Black))));
+
+        // Two jokers wrapping around either end of the run, each one can stand for
+        // any of the pieces some way of filling the run leaves to it
+        let rules = RuleSet { wrap: Wrap::Around, ..RuleSet::default() };
+        let two = resolve_jokers_with_rules(&[
+            Piece::Joker,
+            Piece::Joker,
+            Piece::normal(12, Color::Red),
+            Piece::normal(13, Color::Red),
+        ], &rules).unwrap();
+
+        for joker in &two[..2] {
+            let mut options = joker.options.concat();
+            options.sort_by_key(|normal| normal.domination);
+
+            assert_eq!(options, vec!(normal(1, Color::Red), normal(2, Color::Red), normal(10, Color::Red), normal(11, Color::Red)));
+        }
+
+        let rules = RuleSet { special_jokers: true, ..RuleSet::default() };
+        let double = resolve_jokers_with_rules(&[Piece::normal(12, Color::Red), Piece::DoubleJoker], &rules).unwrap();
+        assert_eq!(double[1].options[0], vec!(normal(12, Color::Black), normal(12, Color::Blue)));
+        assert!(double[1].options.contains(&vec!(normal(10, Color::Red), normal(11, Color::Red))));
+
+        assert_eq!(resolve_jokers(&[Piece::normal(1, Color::Red), Piece::Joker]), Err(RummikubError::InvalidSet));
+        assert_eq!(resolve_jokers(&[Piece::Joker, Piece::Joker, Piece::Joker]), Err(RummikubError::JokerOnlySet));
+
+        // A joker past a full lap of the run has nothing left to stand for
+        let mut lap: Vec<Piece> = (1..14).map(|d| Piece::normal(d, Color::Red)).collect();
+        lap.push(Piece::Joker);
+        let wrapped = RuleSet { wrap: Wrap::Around, ..RuleSet::default() };
+        assert_eq!(resolve_jokers_with_rules(&lap, &wrapped), Err(RummikubError::InvalidSet));
+    }
 
     #[test]
     fn sort_same_domination_set() {
@@ -159,7 +427,7 @@ mod tests {
             Piece::normal(1, Color::Orange),
             Piece::normal(1, Color::Black),
             Piece::normal(1, Color::Blue),
-        )), SetOrder {
+        ), &RuleSet::default()).unwrap(), SetOrder {
             effective_domination: 1,
             effective_color: Color::Black,
             set: vec!(
@@ -175,7 +443,7 @@ mod tests {
             Piece::normal(1, Color::Blue),
             Piece::Joker,
             Piece::Joker,
-        )), SetOrder {
+        ), &RuleSet::default()).unwrap(), SetOrder {
             effective_domination: 1,
             effective_color: Color::Black,
             set: vec!(
@@ -191,7 +459,7 @@ mod tests {
             Piece::Joker,
             Piece::normal(1, Color::Black),
             Piece::Joker,
-        )), SetOrder {
+        ), &RuleSet::default()).unwrap(), SetOrder {
             effective_domination: 1,
             effective_color: Color::Black,
             set: vec!(
@@ -209,7 +477,7 @@ mod tests {
             Piece::normal(3, Color::Red),
             Piece::normal(2, Color::Red),
             Piece::normal(1, Color::Red),
-        )), SetOrder {
+        ), &RuleSet::default()).unwrap(), SetOrder {
             effective_domination: 1,
             effective_color: Color::Red,
             set: vec!(
@@ -223,7 +491,7 @@ mod tests {
             Piece::normal(3, Color::Red),
             Piece::normal(2, Color::Red),
             Piece::Joker,
-        )), SetOrder {
+        ), &RuleSet::default()).unwrap(), SetOrder {
             effective_domination: 1,
             effective_color: Color::Red,
             set: vec!(
@@ -237,7 +505,7 @@ mod tests {
             Piece::normal(3, Color::Red),
             Piece::Joker,
             Piece::normal(1, Color::Red),
-        )), SetOrder {
+        ), &RuleSet::default()).unwrap(), SetOrder {
             effective_domination: 1,
             effective_color: Color::Red,
             set: vec!(
@@ -261,7 +529,7 @@ mod tests {
             Piece::normal(10, Color::Red),
             Piece::normal(9, Color::Red),
             Piece::Joker
-        )), SetOrder {
+        ), &RuleSet::default()).unwrap(), SetOrder {
             effective_domination: 1,
             effective_color: Color::Red,
             set: vec!(
@@ -286,7 +554,7 @@ mod tests {
             Piece::Joker,
             Piece::normal(11, Color::Red),
             Piece::Joker,
-        )), SetOrder {
+        ), &RuleSet::default()).unwrap(), SetOrder {
             effective_domination: 10,
             effective_color: Color::Red,
             set: vec!(
@@ -298,6 +566,101 @@ mod tests {
         });
     }
 
+    #[test]
+    fn sort_wrapped_runs() {
+        let rules = RuleSet { wrap: Wrap::Around, ..RuleSet::default() };
+
+        assert_eq!(SetOrder::new(vec!(
+            Piece::normal(1, Color::Blue),
+            Piece::Joker,
+            Piece::normal(12, Color::Blue),
+        ), &rules).unwrap(), SetOrder {
+            effective_domination: 12,
+            effective_color: Color::Blue,
+            set: vec!(
+                Piece::normal(12, Color::Blue),
+                Piece::Joker,
+                Piece::normal(1, Color::Blue),
+            ),
+        });
+
+        assert_eq!(effective_dominations(&[
+            Piece::normal(2, Color::Blue),
+            Piece::Joker,
+            Piece::normal(13, Color::Blue),
+            Piece::Joker,
+        ], &rules), vec!(2, 12, 13, 1));
+    }
+
+    #[test]
+    fn sort_sets_with_special_jokers() {
+        let rules = RuleSet { special_jokers: true, ..RuleSet::default() };
+
+        assert_eq!(SetOrder::new(vec!(
+            Piece::normal(5, Color::Red),
+            Piece::normal(4, Color::Red),
+            Piece::normal(4, Color::Red),
+            Piece::MirrorJoker,
+            Piece::normal(5, Color::Red),
+        ), &rules).unwrap().set, vec!(
+            Piece::normal(4, Color::Red),
+            Piece::normal(5, Color::Red),
+            Piece::MirrorJoker,
+            Piece::normal(5, Color::Red),
+            Piece::normal(4, Color::Red),
+        ));
+
+        let double = vec!(Piece::normal(4, Color::Blue), Piece::DoubleJoker, Piece::normal(1, Color::Blue));
+        assert_eq!(SetOrder::new(double.clone(), &rules).unwrap(), SetOrder {
+            effective_domination: 1,
+            effective_color: Color::Blue,
+            set: vec!(Piece::normal(1, Color::Blue), Piece::DoubleJoker, Piece::normal(4, Color::Blue)),
+        });
+        assert_eq!(effective_dominations(&double, &rules), vec!(4, 5, 1));
+
+        let color_change = vec!(
+            Piece::normal(7, Color::Black),
+            Piece::normal(4, Color::Red),
+            Piece::ColorChangeJoker,
+            Piece::normal(6, Color::Black),
+        );
+        assert_eq!(SetOrder::new(color_change.clone(), &rules).unwrap().set, vec!(
+            Piece::normal(4, Color::Red),
+            Piece::ColorChangeJoker,
+            Piece::normal(6, Color::Black),
+            Piece::normal(7, Color::Black),
+        ));
+        assert_eq!(effective_dominations(&color_change, &rules), vec!(7, 4, 5, 6));
+    }
+
+    #[test]
+    fn reject_sets_that_cant_be_ordered() {
+        assert_eq!(SetOrder::new(vec!(Piece::Joker, Piece::Joker, Piece::Joker), &RuleSet::default()), Err(RummikubError::JokerOnlySet));
+        assert_eq!(SetOrder::new(vec!(
+            Piece::normal(1, Color::Red),
+            Piece::normal(2, Color::Red),
+            Piece::normal(9, Color::Red),
+        ), &RuleSet::default()), Err(RummikubError::InvalidSet));
+    }
+
+    #[test]
+    fn order_jokers_as_a_run_without_colors_left() {
+        let rules = RuleSet { colors: 2, ..RuleSet::default() };
+        let set = vec!(Piece::Joker, Piece::Joker, Piece::normal(4, Color::Blue));
+
+        assert_eq!(SetOrder::new(set.clone(), &rules).unwrap().effective_domination, 2);
+        assert_eq!(effective_dominations(&set, &rules), vec!(2, 3, 4));
+        assert!(resolve_jokers_with_rules(&set, &rules).is_ok());
+        assert_eq!(solve_board_with_rules(set.clone(), &rules).unwrap().map(|sets| sets.concat().len()), Some(3));
+
+        let rules = RuleSet { colors: 3, jokers: 3, ..RuleSet::default() };
+        let set = vec!(Piece::Joker, Piece::Joker, Piece::Joker, Piece::normal(4, Color::Blue));
+
+        assert_eq!(effective_dominations(&set, &rules), vec!(1, 2, 3, 4));
+        assert!(resolve_jokers_with_rules(&set, &rules).is_ok());
+        assert_eq!(solve_board_with_rules(set, &rules).unwrap().map(|sets| sets.concat().len()), Some(4));
+    }
+
     #[test]
     fn test_sort_sets() {
         assert_eq!(sort_sets(vec!(
@@ -316,7 +679,7 @@ mod tests {
                 Piece::normal(3, Color::Black),
                 Piece::Joker,
             )
-        )), vec!(
+        ), &RuleSet::default()).unwrap(), vec!(
             vec!(
                 Piece::normal(1, Color::Black),
                 Piece::normal(2, Color::Black),
@@ -0,0 +1,99 @@
+use crate::{Piece, RuleSet, RummikubError};
+use crate::deck::TileBag;
+use crate::solve_tile::{join_runs, prepare, Search, solve_board_with_rules};
+use crate::solve_turn::{solve_turn_with_rules, TurnGoal};
+use crate::sort_set::sort_sets;
+use crate::validate::valid_set_with_rules;
+
+/// Anything that plays like a piece, as cards do in Rummy. Wild tiles play as jokers.
+pub trait Tile: Copy + PartialEq {
+    /// The piece the tile plays as under the rules
+    fn piece(&self, rules: &RuleSet) -> Piece;
+
+    /// Points the tile is worth, tiles playing as the same piece have to be worth
+    /// the same
+    fn value(&self, rules: &RuleSet) -> u32;
+}
+
+impl Tile for Piece {
+    fn piece(&self, _rules: &RuleSet) -> Piece {
+        *self
+    }
+
+    fn value(&self, _rules: &RuleSet) -> u32 {
+        Piece::value(self) as u32
+    }
+}
+
+/// A hand of tiles split into the sets found and the tiles left out
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Melds<T> {
+    pub sets: Vec<Vec<T>>,
+    pub left: Vec<T>,
+}
+
+fn pieces<T: Tile>(tiles: &[T], rules: &RuleSet) -> Vec<Piece> {
+    tiles.iter().map(|tile| tile.piece(rules)).collect()
+}
+
+// Swaps the pieces of every set back for the tiles they came from, taking the
+// tiles out of `tiles`
+fn to_tiles<T: Tile>(sets: Vec<Vec<Piece>>, tiles: &mut Vec<T>, rules: &RuleSet) -> Vec<Vec<T>> {
+    sets.iter().map(|set| set.iter().map(|piece| {
+        // Every piece of a set was made from one of the tiles
+        let position = tiles.iter().position(|tile| tile.piece(rules) == *piece).unwrap();
+
+        tiles.remove(position)
+    }).collect()).collect()
+}
+
+pub fn valid_tile_set<T: Tile>(tiles: &[T], rules: &RuleSet) -> bool {
+    valid_set_with_rules(&pieces(tiles, rules), rules)
+}
+
+/// Same as `solve_board_with_rules`, for tiles. Errors are about the pieces the tiles play as.
+pub fn solve_tiles<T: Tile>(tiles: Vec<T>, rules: &RuleSet) -> Result<Option<Vec<Vec<T>>>, RummikubError> {
+    let mut left = tiles;
+
+    Ok(solve_board_with_rules(pieces(&left, rules), rules)?
+        .map(|sets| to_tiles(sets, &mut left, rules)))
+}
+
+/// Arranges the tiles into sets scoring the most under the goal, leaving out the rest
+pub fn meld_tiles<T: Tile>(tiles: Vec<T>, goal: TurnGoal, rules: &RuleSet) -> Result<Melds<T>, RummikubError> {
+    let mut left = tiles;
+    let sets = match solve_turn_with_rules(vec!(), pieces(&left, rules), goal, rules)? {
+        // There's no board, so there's always an arrangement
+        None => vec!(),
+        Some(turn) => to_tiles(turn.sets, &mut left, rules)
+    };
+
+    Ok(Melds { sets, left })
+}
+
+/// Arranges the tiles into the sets worth the most by `Tile::value`, leaving out the rest
+pub fn meld_tiles_by_value<T: Tile>(tiles: Vec<T>, rules: &RuleSet) -> Result<Melds<T>, RummikubError> {
+    rules.check()?;
+
+    let pieces = pieces(&tiles, rules);
+    TileBag::for_rules(rules).check(&pieces)?;
+
+    let mut values = vec!(0; rules.kinds());
+
+    for (piece, tile) in pieces.iter().zip(&tiles) {
+        values[rules.kind(piece).expect("the pieces were checked")] = tile.value(rules);
+    }
+
+    let (state, catalogue) = prepare(&[], &pieces, rules);
+    let mut search = Search::new(catalogue, TurnGoal::MostTiles);
+    search.value_kinds(values);
+
+    let mut left = tiles;
+    let sets = match search.cover(state) {
+        // Nothing has to be placed, so there's always an arrangement
+        None => vec!(),
+        Some(found) => to_tiles(sort_sets(join_runs(found.sets, rules), rules)?, &mut left, rules)
+    };
+
+    Ok(Melds { sets, left })
+}
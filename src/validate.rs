@@ -1,22 +1,24 @@
+use itertools::Itertools;
+
 use crate::common::{ConsecutiveSet, first_non_joker, same_color, same_domination};
-use crate::Piece;
+use crate::{NormalPiece, Piece, RuleSet, RummikubError};
 use crate::piece::Color;
 
-fn consecutive(pieces: &Vec<Piece>) -> bool {
-    // The values are 1 to 13, so you can't have more than that,
+fn consecutive(pieces: &Vec<Piece>, rules: &RuleSet) -> bool {
+    // The values are 1 to 13 by default, so you can't have more than that,
     // even if including jokers(because jokers will have to be duplicates)
-    if pieces.len() > 13 {
-        return false;
+    if pieces.len() > rules.max_run_length() {
+        false
     } else {
-        match ConsecutiveSet::new(pieces) {
+        match ConsecutiveSet::new(pieces, rules) {
             None => false,
             Some(set) => set.is_valid()
         }
     }
 }
 
-fn repeating_colors(pieces: &Vec<Piece>) -> bool {
-    if pieces.len() > 4 {
+fn repeating_colors(pieces: &Vec<Piece>, rules: &RuleSet) -> bool {
+    if pieces.len() > rules.max_group_length() {
         return true;
     }
 
@@ -65,31 +67,369 @@ fn repeating_colors(pieces: &Vec<Piece>) -> bool {
     false
 }
 
+/// All members are the same domination but different colors, whatever the length of the set
+pub(crate) fn is_group(first_piece: &NormalPiece, pieces: &Vec<Piece>, rules: &RuleSet) -> bool {
+    same_domination(first_piece, pieces) && !repeating_colors(pieces, rules)
+}
+
+/// All members are the same color and consecutive, whatever the length of the set
+pub(crate) fn is_run(first_piece: &NormalPiece, pieces: &Vec<Piece>, rules: &RuleSet) -> bool {
+    same_color(first_piece, pieces) && consecutive(pieces, rules)
+}
+
+fn run_or_group(pieces: &Vec<Piece>, rules: &RuleSet) -> bool {
+    if let Some(first_piece) = first_non_joker(pieces) {
+        is_group(first_piece, pieces, rules) || is_run(first_piece, pieces, rules)
+    } else {
+        // A set full of jokers doesn't stand for anything
+        false
+    }
+}
+
+// A run where the pieces before the color change joker share a color, and so do
+// the pieces after it
+fn color_change_run(pieces: &Vec<Piece>, joker: &NormalPiece, rules: &RuleSet) -> bool {
+    if !consecutive(pieces, rules) {
+        return false;
+    }
+
+    // The run is valid, so it has a domination for every piece
+    let set = ConsecutiveSet::new(pieces, rules).unwrap();
+    let position = set.offset(joker.domination);
+    let one_color = |side: Vec<&NormalPiece>| side.iter().all(|n| n.color == side[0].color);
+
+    let normals: Vec<&NormalPiece> = pieces.iter().filter_map(|piece| match piece {
+        Piece::Normal(normal) => Some(normal),
+        _ => None
+    }).collect();
+
+    one_color(normals.iter().copied().filter(|n| set.offset(n.domination) < position).collect()) &&
+        one_color(normals.iter().copied().filter(|n| set.offset(n.domination) > position).collect())
+}
+
+/// The pieces on one side of the mirror joker, when the pieces on the other side are the same
+pub(crate) fn mirrored_half(pieces: &[Piece]) -> Option<Vec<Piece>> {
+    let mut rest = pieces.to_vec();
+    rest.remove(rest.iter().position(|piece| *piece == Piece::MirrorJoker)?);
+
+    let mut half = Vec::new();
+
+    while let Some(piece) = rest.pop() {
+        rest.remove(rest.iter().position(|p| *p == piece)?);
+        half.push(piece);
+    }
+
+    Some(half)
+}
+
+/// The normal pieces the double or color change joker of a set stands for, making
+/// it a valid run or group. The lowest dominations are tried first.
+pub(crate) fn stand_ins(pieces: &[Piece], rules: &RuleSet) -> Option<Vec<NormalPiece>> {
+    all_stand_ins(pieces, rules).into_iter().next()
+}
+
+/// Every choice of normal pieces the double or color change joker of a set can stand for
+pub(crate) fn all_stand_ins(pieces: &[Piece], rules: &RuleSet) -> Vec<Vec<NormalPiece>> {
+    let special = match pieces.iter().find(|piece| piece.is_special_joker()) {
+        None => return vec!(),
+        Some(special) => *special
+    };
+    let others: Vec<Piece> = pieces.iter().filter(|piece| !piece.is_special_joker()).copied().collect();
+    let first = match first_non_joker(&others) {
+        None => return vec!(),
+        Some(first) => *first
+    };
+
+    let with = |stand_ins: &[NormalPiece]| {
+        let mut set = others.clone();
+        set.extend(stand_ins.iter().map(|normal| Piece::Normal(*normal)));
+        set
+    };
+    let in_color = |domination| NormalPiece { domination, color: first.color };
+
+    match special {
+        Piece::DoubleJoker => {
+            let mut result: Vec<Vec<NormalPiece>> = Vec::new();
+
+            // Two of the colors missing from a group
+            if same_domination(&first, &others) {
+                let missing = rules.colors().iter()
+                    .filter(|color| !others.iter().any(|p| matches!(p, Piece::Normal(n) if n.color == **color)))
+                    .map(|color| NormalPiece { domination: first.domination, color: *color });
+
+                result.extend(missing.combinations(2).filter(|stand_ins| run_or_group(&with(stand_ins), rules)));
+            }
+
+            // Or two pieces next to each other in a run
+            result.extend(rules.dominations()
+                .filter_map(|domination| Some(vec!(in_color(domination), in_color(rules.next_domination(domination)?))))
+                .filter(|stand_ins| run_or_group(&with(stand_ins), rules)));
+
+            result
+        }
+        Piece::ColorChangeJoker => rules.dominations()
+            .map(|domination| vec!(in_color(domination)))
+            .filter(|stand_ins| color_change_run(&with(stand_ins), &stand_ins[0], rules))
+            .collect(),
+        _ => vec!()
+    }
+}
+
+/// A set made only of jokers is never valid, `check_set` tells it apart
 pub fn valid_set(pieces: &Vec<Piece>) -> bool {
-    if pieces.len() < 3 {
+    valid_set_with_rules(pieces, &RuleSet::default())
+}
+
+pub fn valid_set_with_rules(pieces: &Vec<Piece>, rules: &RuleSet) -> bool {
+    // A double joker counts as the two pieces it stands for
+    let length = pieces.len() + pieces.iter().filter(|piece| **piece == Piece::DoubleJoker).count();
+
+    if length < rules.min_set_length as usize {
         return false;
     }
 
-    // Check if all members are the same domination but different colors
-    if let Some(first_piece) = first_non_joker(&pieces) {
-        return (same_domination(first_piece, &pieces) && !repeating_colors(&pieces)) ||
-            (same_color(first_piece, &pieces) && consecutive(&pieces));
-    } else {
-        panic!("A set full of jokers that is more than 3 pieces in size detected");
+    if pieces.iter().any(|piece| rules.check_piece(piece).is_err()) {
+        return false;
+    }
+
+    match pieces.iter().filter(|piece| piece.is_special_joker()).count() {
+        0 => run_or_group(pieces, rules),
+        // Special jokers don't share a set, not even with a joker
+        _ if pieces.contains(&Piece::Joker) => false,
+        // A mirror needs at least two pieces on either side
+        1 if pieces.contains(&Piece::MirrorJoker) =>
+            mirrored_half(pieces).is_some_and(|half| half.len() >= 2 && run_or_group(&half, rules)),
+        1 => stand_ins(pieces, rules).is_some(),
+        // Special jokers don't share a set
+        _ => false
     }
 }
 
+pub fn check_set(pieces: &[Piece]) -> Result<(), RummikubError> {
+    check_set_with_rules(pieces, &RuleSet::default())
+}
+
+/// Same as `valid_set_with_rules`, but with the reason the set isn't valid
+pub fn check_set_with_rules(pieces: &[Piece], rules: &RuleSet) -> Result<(), RummikubError> {
+    if let Some(piece) = pieces.iter().find(|piece| rules.check_piece(piece).is_err()) {
+        return Err(RummikubError::InvalidTile(*piece));
+    }
+
+    if !pieces.is_empty() && pieces.iter().all(|piece| *piece == Piece::Joker) {
+        return Err(RummikubError::JokerOnlySet);
+    }
+
+    if !valid_set_with_rules(&pieces.to_vec(), rules) {
+        return Err(RummikubError::InvalidSet);
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
-    use crate::Piece;
+    use crate::{Piece, RuleSet, RummikubError, Wrap};
     use crate::piece::Color;
-    use crate::validate::valid_set;
+    use crate::validate::{check_set, valid_set, valid_set_with_rules};
 
     #[test]
     fn reject_small_sets() {
         assert_eq!(valid_set(&vec!()), false);
     }
 
+    #[test]
+    fn follow_the_rules() {
+        let rules = RuleSet { highest_domination: 9, colors: 3, min_set_length: 4, ..RuleSet::default() };
+
+        assert!(valid_set_with_rules(&vec!(
+            Piece::normal(7, Color::Red),
+            Piece::normal(8, Color::Red),
+            Piece::normal(9, Color::Red),
+            Piece::Joker,
+        ), &rules));
+
+        // Too short
+        assert!(!valid_set_with_rules(&vec!(
+            Piece::normal(7, Color::Red),
+            Piece::normal(8, Color::Red),
+            Piece::normal(9, Color::Red),
+        ), &rules));
+
+        // A color the rules don't have
+        assert!(!valid_set_with_rules(&vec!(
+            Piece::normal(7, Color::Black),
+            Piece::normal(7, Color::Blue),
+            Piece::normal(7, Color::Red),
+            Piece::normal(7, Color::Orange),
+        ), &rules));
+
+        // Only three colors, so a group can't be any larger
+        assert!(!valid_set_with_rules(&vec!(
+            Piece::normal(7, Color::Black),
+            Piece::normal(7, Color::Blue),
+            Piece::normal(7, Color::Red),
+            Piece::Joker,
+        ), &rules));
+
+        // Runs can't go past the highest domination
+        assert!(!valid_set_with_rules(&vec!(
+            Piece::normal(1, Color::Red),
+            Piece::normal(2, Color::Red),
+            Piece::normal(3, Color::Red),
+            Piece::normal(4, Color::Red),
+            Piece::normal(5, Color::Red),
+            Piece::normal(6, Color::Red),
+            Piece::normal(7, Color::Red),
+            Piece::normal(8, Color::Red),
+            Piece::normal(9, Color::Red),
+            Piece::Joker,
+        ), &rules));
+    }
+
+    #[test]
+    fn allow_runs_to_wrap_around() {
+        let rules = RuleSet { wrap: Wrap::Around, ..RuleSet::default() };
+        let wrapped = vec!(
+            Piece::normal(12, Color::Red),
+            Piece::normal(13, Color::Red),
+            Piece::normal(1, Color::Red),
+        );
+
+        assert!(!valid_set(&wrapped));
+        assert!(valid_set_with_rules(&wrapped, &rules));
+
+        assert!(valid_set_with_rules(&vec!(
+            Piece::normal(13, Color::Red),
+            Piece::Joker,
+            Piece::normal(2, Color::Red),
+            Piece::normal(3, Color::Red),
+        ), &rules));
+
+        // Still a single lap at most
+        let mut lap: Vec<Piece> = (1..14).map(|d| Piece::normal(d, Color::Red)).collect();
+        assert!(valid_set_with_rules(&lap, &rules));
+        lap.push(Piece::Joker);
+        assert!(!valid_set_with_rules(&lap, &rules));
+
+        assert!(!valid_set_with_rules(&vec!(
+            Piece::normal(11, Color::Red),
+            Piece::normal(1, Color::Red),
+            Piece::normal(3, Color::Red),
+        ), &rules));
+    }
+
+    #[test]
+    fn allow_only_the_lowest_after_the_highest() {
+        let rules = RuleSet { wrap: Wrap::LowestAfterHighest, ..RuleSet::default() };
+
+        assert!(valid_set_with_rules(&vec!(
+            Piece::normal(12, Color::Red),
+            Piece::normal(13, Color::Red),
+            Piece::normal(1, Color::Red),
+        ), &rules));
+
+        assert!(valid_set_with_rules(&vec!(
+            Piece::normal(1, Color::Red),
+            Piece::Joker,
+            Piece::normal(12, Color::Red),
+        ), &rules));
+
+        assert!(!valid_set_with_rules(&vec!(
+            Piece::normal(13, Color::Red),
+            Piece::normal(1, Color::Red),
+            Piece::normal(2, Color::Red),
+        ), &rules));
+    }
+
+    #[test]
+    fn allow_special_jokers() {
+        let rules = RuleSet { special_jokers: true, ..RuleSet::default() };
+        let mirrored = vec!(
+            Piece::normal(4, Color::Red),
+            Piece::normal(5, Color::Red),
+            Piece::MirrorJoker,
+            Piece::normal(5, Color::Red),
+            Piece::normal(4, Color::Red),
+        );
+
+        // Only when the game has them
+        assert!(!valid_set(&mirrored));
+        assert!(valid_set_with_rules(&mirrored, &rules));
+
+        // Both sides have to be the same, and a run or a group
+        assert!(!valid_set_with_rules(&vec!(
+            Piece::normal(4, Color::Red),
+            Piece::normal(5, Color::Red),
+            Piece::MirrorJoker,
+            Piece::normal(6, Color::Red),
+            Piece::normal(4, Color::Red),
+        ), &rules));
+        assert!(!valid_set_with_rules(&vec!(
+            Piece::normal(4, Color::Red),
+            Piece::normal(6, Color::Red),
+            Piece::MirrorJoker,
+            Piece::normal(6, Color::Red),
+            Piece::normal(4, Color::Red),
+        ), &rules));
+
+        // The double joker counts as two pieces next to each other
+        assert!(valid_set_with_rules(&vec!(Piece::normal(9, Color::Blue), Piece::DoubleJoker), &rules));
+        assert!(valid_set_with_rules(&vec!(
+            Piece::normal(1, Color::Blue),
+            Piece::DoubleJoker,
+            Piece::normal(4, Color::Blue),
+        ), &rules));
+        assert!(!valid_set_with_rules(&vec!(
+            Piece::normal(1, Color::Blue),
+            Piece::DoubleJoker,
+            Piece::normal(3, Color::Blue),
+            Piece::normal(5, Color::Blue),
+        ), &rules));
+        assert!(!valid_set_with_rules(&vec!(
+            Piece::normal(9, Color::Blue),
+            Piece::normal(9, Color::Red),
+            Piece::normal(9, Color::Black),
+            Piece::DoubleJoker,
+        ), &rules));
+
+        // The color of a run changes once, at the color change joker
+        assert!(valid_set_with_rules(&vec!(
+            Piece::normal(3, Color::Red),
+            Piece::normal(4, Color::Red),
+            Piece::ColorChangeJoker,
+            Piece::normal(6, Color::Black),
+            Piece::normal(7, Color::Black),
+        ), &rules));
+        assert!(!valid_set_with_rules(&vec!(
+            Piece::normal(3, Color::Red),
+            Piece::normal(4, Color::Black),
+            Piece::ColorChangeJoker,
+            Piece::normal(6, Color::Black),
+        ), &rules));
+        assert!(!valid_set_with_rules(&vec!(
+            Piece::normal(3, Color::Red),
+            Piece::normal(3, Color::Black),
+            Piece::ColorChangeJoker,
+        ), &rules));
+
+        assert!(!valid_set_with_rules(&vec!(
+            Piece::normal(3, Color::Red),
+            Piece::ColorChangeJoker,
+            Piece::DoubleJoker,
+        ), &rules));
+        assert!(!valid_set_with_rules(&vec!(Piece::normal(5, Color::Red), Piece::Joker, Piece::ColorChangeJoker), &rules));
+        assert!(!valid_set_with_rules(&vec!(Piece::normal(5, Color::Red), Piece::Joker, Piece::DoubleJoker), &rules));
+    }
+
+    #[test]
+    fn reject_joker_only_sets() {
+        assert!(!valid_set(&vec!(Piece::Joker, Piece::Joker, Piece::Joker)));
+        assert_eq!(check_set(&[Piece::Joker, Piece::Joker, Piece::Joker]), Err(RummikubError::JokerOnlySet));
+        assert_eq!(check_set(&[Piece::normal(1, Color::Red), Piece::Joker]), Err(RummikubError::InvalidSet));
+        assert_eq!(check_set(&[Piece::normal(1, Color::Red), Piece::Joker, Piece::Joker]), Ok(()));
+    }
+
     #[test]
     fn allow_consecutive() {
         // No jokers
@@ -0,0 +1,48 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn run(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rummikub"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn print_solved_sets() {
+    let output = run(&[], "R3 R1 R2 B5 O5 K5");
+
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "[R1 R2 R3]\n[K5 B5 O5]\n");
+}
+
+#[test]
+fn read_json_pieces() {
+    let output = run(&["--json"], r#"[
+        {"type":"Normal","domination":1,"color":"Red"},
+        {"type":"Normal","domination":2,"color":"Red"},
+        {"type":"Joker"}
+    ]"#);
+
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn fail_on_unsolvable_pieces() {
+    assert_eq!(run(&[], "R1 R2").status.code(), Some(1));
+}
+
+#[test]
+fn reject_invalid_input() {
+    assert_eq!(run(&[], "R1 X2").status.code(), Some(2));
+    assert_eq!(run(&[], "R1 R1 R1").status.code(), Some(2));
+    assert_eq!(run(&[], "").status.code(), Some(2));
+    assert_eq!(run(&[], "[]").status.code(), Some(2));
+    assert_eq!(run(&[], "  \n").status.code(), Some(2));
+}